@@ -1,3 +1,4 @@
+use crate::config::Action;
 use crate::config::Config;
 use crate::config::Mode;
 use crate::input::Key;
@@ -6,13 +7,17 @@ use chrono::{DateTime, Utc};
 use mime_guess;
 use serde::{Deserialize, Serialize};
 use std::cmp::Ordering;
+use std::collections::BTreeMap;
 use std::collections::BinaryHeap;
 use std::collections::HashMap;
+use std::collections::HashSet;
 use std::collections::VecDeque;
 use std::env;
 use std::fs;
 use std::io;
-use std::path::PathBuf;
+use std::os::unix::fs::PermissionsExt;
+use std::path::{Component, Path, PathBuf};
+use std::time::Duration;
 
 pub const VERSION: &str = "v0.2.19"; // Update Cargo.toml
 
@@ -20,6 +25,26 @@ pub const TEMPLATE_TABLE_ROW: &str = "TEMPLATE_TABLE_ROW";
 
 pub const UNSUPPORTED_STR: &str = "???";
 
+/// The longest a `Sleep` message can block the app, in milliseconds.
+pub const MAX_SLEEP_MILLIS: u64 = 10_000;
+
+/// The errors that are not really failures, but the ways the app ends.
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub enum AppError {
+    /// Terminated by the user, e.g. via the `Terminate` message.
+    Terminated,
+}
+
+impl std::fmt::Display for AppError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Terminated => write!(f, "terminated"),
+        }
+    }
+}
+
+impl std::error::Error for AppError {}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Pipe {
     pub msg_in: String,
@@ -54,43 +79,99 @@ impl Pipe {
             mode_out,
         }
     }
+
+    /// Write the mode name to `mode_out` atomically, so that the readers never
+    /// see a partially written name.
+    pub fn write_mode_out(&self, mode: &str) -> io::Result<()> {
+        let tmp = format!("{}.tmp", &self.mode_out);
+        fs::write(&tmp, mode)?;
+        fs::rename(&tmp, &self.mode_out)
+    }
 }
 
-#[derive(Debug, Clone, Eq, PartialEq, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Node {
     pub parent: String,
     pub relative_path: String,
     pub absolute_path: String,
     pub extension: String,
+    /// The lowercase version of `extension`, for case insensitive matching.
+    #[serde(default)]
+    pub extension_lower: String,
     pub is_symlink: bool,
     pub is_dir: bool,
     pub is_file: bool,
     pub is_readonly: bool,
     pub mime_essence: String,
+    /// The raw (possibly relative) target of the symlink, if it's one.
+    #[serde(default)]
+    pub symlink_target: Option<String>,
+}
+
+/// Whether the absolute paths of the nodes should resolve the symlinks.
+///
+/// `Follow` makes entering a symlinked directory jump to where the target
+/// really lives, so `Back` goes to the target's parent. `DontFollow` keeps the
+/// path as it was browsed, so `Back` returns to where the link is, but the same
+/// file can then appear under different paths (e.g. in the selection).
+#[derive(Debug, Clone, Copy, Default, Eq, PartialEq, Serialize, Deserialize)]
+pub enum SymlinkResolution {
+    #[default]
+    Follow,
+    DontFollow,
+}
+
+/// How the paths of the nodes are displayed. The absolute path stays the same.
+#[derive(Debug, Clone, Copy, Default, Eq, PartialEq, Serialize, Deserialize)]
+pub enum PathDisplay {
+    #[default]
+    Absolute,
+    RelativeToPwd,
+    HomeTilde,
 }
 
 impl Node {
     pub fn new(parent: String, relative_path: String) -> Self {
-        let absolute_path = PathBuf::from(&parent)
-            .join(&relative_path)
-            .canonicalize()
-            .unwrap_or_default()
-            .to_string_lossy()
-            .to_string();
+        Self::with_symlink_resolution(parent, relative_path, SymlinkResolution::Follow)
+    }
+
+    pub fn with_symlink_resolution(
+        parent: String,
+        relative_path: String,
+        symlink_resolution: SymlinkResolution,
+    ) -> Self {
+        let lexical_path = PathBuf::from(&parent).join(&relative_path);
+        let absolute_path = match symlink_resolution {
+            SymlinkResolution::Follow => lexical_path.canonicalize().unwrap_or_default(),
+            SymlinkResolution::DontFollow => lexical_path.clone(),
+        }
+        .to_string_lossy()
+        .to_string();
 
         let path = PathBuf::from(&absolute_path);
 
+        // Canonicalization resolves the links, so check the path as it is.
+        let is_symlink = lexical_path
+            .symlink_metadata()
+            .map(|m| m.file_type().is_symlink())
+            .unwrap_or(false);
+
+        let symlink_target = if is_symlink {
+            fs::read_link(&lexical_path)
+                .ok()
+                .map(|t| t.to_string_lossy().to_string())
+        } else {
+            None
+        };
+
         let extension = path
             .extension()
             .map(|e| e.to_string_lossy().to_string())
             .unwrap_or_default();
 
-        let maybe_metadata = path.metadata().ok();
+        let extension_lower = extension.to_lowercase();
 
-        let is_symlink = maybe_metadata
-            .clone()
-            .map(|m| m.file_type().is_symlink())
-            .unwrap_or(false);
+        let maybe_metadata = path.metadata().ok();
 
         let is_dir = maybe_metadata.clone().map(|m| m.is_dir()).unwrap_or(false);
 
@@ -110,21 +191,180 @@ impl Node {
             relative_path,
             absolute_path,
             extension,
+            extension_lower,
             is_symlink,
             is_dir,
             is_file,
             is_readonly,
             mime_essence,
+            symlink_target,
+        }
+    }
+}
+
+impl Node {
+    /// Render the given template, replacing the `{path}`, `{name}`, `{size}`,
+    /// `{mime}` and `{is_dir}` placeholders. Unknown placeholders are kept as is,
+    /// and `{size}` becomes empty if it can't be read.
+    pub fn format(&self, template: &str) -> String {
+        let size = fs::metadata(&self.absolute_path)
+            .map(|m| m.len().to_string())
+            .unwrap_or_default();
+
+        template
+            .replace("{path}", &self.absolute_path)
+            .replace("{name}", &self.relative_path)
+            .replace("{size}", &size)
+            .replace("{mime}", &self.mime_essence)
+            .replace("{is_dir}", &self.is_dir.to_string())
+    }
+
+    /// Get a stable category for the node, for the config to map icons to.
+    /// It's one of `symlink`, `directory`, `image`, `video`, `audio`, the
+    /// lowercase extension, or `file`, in that order of precedence.
+    pub fn icon_key(&self) -> &str {
+        let mime_type = self.mime_essence.split('/').next().unwrap_or_default();
+        if self.is_symlink {
+            "symlink"
+        } else if self.is_dir {
+            "directory"
+        } else if mime_type == "image" || mime_type == "video" || mime_type == "audio" {
+            mime_type
+        } else if !self.extension_lower.is_empty() {
+            &self.extension_lower
+        } else {
+            "file"
+        }
+    }
+
+    /// Get the path to display. Relative to the present working directory, it
+    /// falls back to the absolute path when there's no relative path, and `~`
+    /// only replaces the home directory when the node is in it.
+    pub fn display_path(&self, display: PathDisplay, pwd: &str, home: Option<&str>) -> String {
+        let path = Path::new(&self.absolute_path);
+        match display {
+            PathDisplay::Absolute => None,
+            PathDisplay::RelativeToPwd => relative_path(path, Path::new(pwd)).map(|p| {
+                if p.as_os_str().is_empty() {
+                    ".".into()
+                } else {
+                    p.to_string_lossy().to_string()
+                }
+            }),
+            PathDisplay::HomeTilde => home
+                .and_then(|h| path.strip_prefix(h).ok())
+                .map(|p| Path::new("~").join(p).to_string_lossy().to_string())
+                .map(|p| p.trim_end_matches('/').to_string()),
+        }
+        .unwrap_or_else(|| self.absolute_path.clone())
+    }
+
+    /// Get the name of the node, i.e. the last component of the relative path.
+    pub fn file_name(&self) -> String {
+        Path::new(&self.relative_path)
+            .file_name()
+            .map(|n| n.to_string_lossy().to_string())
+            .unwrap_or_default()
+    }
+
+    /// Get the path of the node as it was browsed, i.e. without resolving the
+    /// symlinks. Unlike the relative path, the parent is the real one, even in
+    /// a recursive listing.
+    pub fn lexical_path(&self) -> PathBuf {
+        PathBuf::from(&self.parent).join(self.file_name())
+    }
+
+    /// Get the number of components in the relative path.
+    pub fn depth(&self) -> usize {
+        Path::new(&self.relative_path).components().count()
+    }
+
+    /// Get everything after the first dot in the name, ignoring the leading
+    /// dots of the hidden files. e.g. `tar.gz` for `archive.tar.gz`, and an
+    /// empty string for `.bashrc`.
+    pub fn full_extension(&self) -> String {
+        let name = self.file_name();
+        let name = name.trim_start_matches('.');
+        name.find('.')
+            .map(|i| name[i + 1..].to_string())
+            .unwrap_or_default()
+    }
+}
+
+/// A node, with the metadata that needs to be read from the disk.
+#[derive(Debug, Clone, Serialize)]
+pub struct NodeMetadata {
+    #[serde(flatten)]
+    pub node: Node,
+    pub size: Option<u64>,
+    pub modified: Option<DateTime<Utc>>,
+    /// The permission bits in octal, e.g. `644`.
+    pub permissions: Option<String>,
+}
+
+impl NodeMetadata {
+    pub fn new(node: Node) -> Self {
+        let metadata = fs::metadata(&node.absolute_path).ok();
+        let size = metadata.as_ref().map(|m| m.len());
+        let modified = metadata
+            .as_ref()
+            .and_then(|m| m.modified().ok())
+            .map(DateTime::<Utc>::from);
+        let permissions = metadata.map(|m| format!("{:o}", m.permissions().mode() & 0o7777));
+
+        Self {
+            node,
+            size,
+            modified,
+            permissions,
         }
     }
 }
 
+/// A summary of the selection, for the scripts that don't need the paths.
+#[derive(Debug, Clone, Default, Eq, PartialEq, Serialize, Deserialize)]
+pub struct SelectionStats {
+    pub count: usize,
+    /// The total size of the selected files. Directories don't count.
+    pub total_bytes: u64,
+    pub directories: usize,
+    pub files: usize,
+    /// The symlinks are also counted as what they point to.
+    pub symlinks: usize,
+}
+
+impl PartialEq for Node {
+    fn eq(&self, other: &Self) -> bool {
+        // The symlink target is only there to be displayed.
+        self.parent == other.parent
+            && self.relative_path == other.relative_path
+            && self.absolute_path == other.absolute_path
+            && self.extension == other.extension
+            && self.is_symlink == other.is_symlink
+            && self.is_dir == other.is_dir
+            && self.is_file == other.is_file
+            && self.is_readonly == other.is_readonly
+            && self.mime_essence == other.mime_essence
+    }
+}
+
+impl Eq for Node {}
+
+/// Nodes are ordered by `relative_path`, then by `is_dir` and `mime_essence`.
+/// The rest of the fields are compared only to break the remaining ties, so
+/// that the ordering stays consistent with `PartialEq`.
 impl Ord for Node {
     fn cmp(&self, other: &Self) -> Ordering {
-        // Notice that the we flip the ordering on costs.
-        // In case of a tie we compare positions - this step is necessary
-        // to make implementations of `PartialEq` and `Ord` consistent.
-        other.relative_path.cmp(&self.relative_path)
+        self.relative_path
+            .cmp(&other.relative_path)
+            .then_with(|| self.is_dir.cmp(&other.is_dir))
+            .then_with(|| self.mime_essence.cmp(&other.mime_essence))
+            .then_with(|| self.absolute_path.cmp(&other.absolute_path))
+            .then_with(|| self.parent.cmp(&other.parent))
+            .then_with(|| self.extension.cmp(&other.extension))
+            .then_with(|| self.is_symlink.cmp(&other.is_symlink))
+            .then_with(|| self.is_file.cmp(&other.is_file))
+            .then_with(|| self.is_readonly.cmp(&other.is_readonly))
     }
 }
 impl PartialOrd for Node {
@@ -136,31 +376,69 @@ impl PartialOrd for Node {
 #[derive(Debug, Clone, Eq, PartialEq, Serialize, Deserialize)]
 pub struct DirectoryBuffer {
     pub parent: String,
+    /// The nodes that passed the filters.
     pub nodes: Vec<Node>,
+    /// All the loaded nodes, including the ones that didn't pass the filters.
+    /// Kept in the dumped state, as the truncation is derived from it.
+    #[serde(default)]
+    pub all_nodes: Vec<Node>,
+    /// The number of nodes that passed the filters.
     pub total: usize,
+    /// The real number of entries in the directory, ignoring the filters.
+    /// Can be greater than the number of `all_nodes` if the directory was
+    /// truncated.
+    #[serde(default)]
+    pub raw_total: usize,
     pub focus: usize,
+    /// Whether the focus was found by the name of the node while exploring,
+    /// rather than defaulted.
+    #[serde(default)]
+    pub focused_by_name: bool,
 }
 
 impl DirectoryBuffer {
-    pub fn new(parent: String, nodes: Vec<Node>, focus: usize) -> Self {
+    pub fn new(parent: String, nodes: Vec<Node>, all_nodes: Vec<Node>, focus: usize) -> Self {
         let total = nodes.len();
+        let raw_total = all_nodes.len();
         Self {
             parent,
             nodes,
+            all_nodes,
             total,
+            raw_total,
             focus,
+            focused_by_name: false,
         }
     }
 
     pub fn focused_node(&self) -> Option<&Node> {
         self.nodes.get(self.focus)
     }
+
+    /// Whether some of the nodes were left out while exploring the directory.
+    pub fn is_truncated(&self) -> bool {
+        self.all_nodes.len() < self.raw_total
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, Eq, PartialEq, Serialize, Deserialize)]
+pub struct ScreenLayout {
+    pub rows: u16,
+    pub cols: u16,
+    pub list_height: u16,
 }
 
 #[derive(Debug, Clone, Eq, PartialEq, Serialize, Deserialize)]
 pub enum InternalMsg {
     AddDirectory(String, DirectoryBuffer),
     HandleKey(Key),
+    /// Sent every `general.tick_interval` milliseconds, if set.
+    Tick,
+    SetLayout {
+        rows: u16,
+        cols: u16,
+        list_height: u16,
+    },
 }
 
 #[derive(Debug, Clone, Copy, Eq, PartialEq, Serialize, Deserialize)]
@@ -188,6 +466,47 @@ pub enum NodeFilter {
 
     AbsolutePathDoesEndWith,
     AbsolutePathDoesNotEndWith,
+
+    FullExtensionIs,
+    FullExtensionIsNot,
+
+    RelativePathDoesMatch,
+    RelativePathDoesNotMatch,
+
+    MimeEssenceDoesStartWith,
+    MimeEssenceDoesNotStartWith,
+}
+
+/// Match the text against a glob pattern, where `*` matches any sequence of
+/// characters and `?` matches a single character.
+fn glob_matches(pattern: &str, text: &str) -> bool {
+    let pattern = pattern.chars().collect::<Vec<char>>();
+    let text = text.chars().collect::<Vec<char>>();
+
+    let (mut p, mut t) = (0, 0);
+    let mut backtrack = None;
+    while t < text.len() {
+        match pattern.get(p) {
+            Some('*') => {
+                backtrack = Some((p, t));
+                p += 1;
+            }
+            Some(c) if *c == '?' || *c == text[t] => {
+                p += 1;
+                t += 1;
+            }
+            _ => match backtrack {
+                // Let the last star eat one more character.
+                Some((star, eaten)) => {
+                    backtrack = Some((star, eaten + 1));
+                    p = star + 1;
+                    t = eaten + 1;
+                }
+                None => return false,
+            },
+        }
+    }
+    pattern[p..].iter().all(|c| *c == '*')
 }
 
 impl NodeFilter {
@@ -350,6 +669,59 @@ impl NodeFilter {
                         .ends_with(&input.to_lowercase())
                 }
             }
+
+            Self::FullExtensionIs => {
+                if case_sensitive {
+                    &node.full_extension() == input
+                } else {
+                    node.full_extension().to_lowercase() == input.to_lowercase()
+                }
+            }
+
+            Self::FullExtensionIsNot => {
+                if case_sensitive {
+                    &node.full_extension() != input
+                } else {
+                    node.full_extension().to_lowercase() != input.to_lowercase()
+                }
+            }
+
+            Self::RelativePathDoesMatch => {
+                if case_sensitive {
+                    glob_matches(input, &node.relative_path)
+                } else {
+                    glob_matches(&input.to_lowercase(), &node.relative_path.to_lowercase())
+                }
+            }
+
+            Self::RelativePathDoesNotMatch => {
+                if case_sensitive {
+                    !glob_matches(input, &node.relative_path)
+                } else {
+                    !glob_matches(&input.to_lowercase(), &node.relative_path.to_lowercase())
+                }
+            }
+
+            Self::MimeEssenceDoesStartWith => {
+                if case_sensitive {
+                    node.mime_essence.starts_with(input)
+                } else {
+                    node.mime_essence
+                        .to_lowercase()
+                        .starts_with(&input.to_lowercase())
+                }
+            }
+
+            Self::MimeEssenceDoesNotStartWith => {
+                if case_sensitive {
+                    !node.mime_essence.starts_with(input)
+                } else {
+                    !node
+                        .mime_essence
+                        .to_lowercase()
+                        .starts_with(&input.to_lowercase())
+                }
+            }
         }
     }
 }
@@ -376,6 +748,44 @@ impl NodeFilterApplicable {
     }
 }
 
+#[derive(Debug, Clone, Copy, Eq, PartialEq, Serialize, Deserialize)]
+pub enum NodeSorter {
+    ByRelativePath,
+    ByExtension,
+    ByMimeEssence,
+}
+
+impl NodeSorter {
+    fn compare(&self, a: &Node, b: &Node) -> Ordering {
+        match self {
+            Self::ByRelativePath => a.relative_path.cmp(&b.relative_path),
+            Self::ByExtension => a.extension_lower.cmp(&b.extension_lower),
+            Self::ByMimeEssence => a.mime_essence.cmp(&b.mime_essence),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Eq, PartialEq, Serialize, Deserialize)]
+pub struct NodeSorterApplicable {
+    sorter: NodeSorter,
+    #[serde(default)]
+    reverse: bool,
+}
+
+impl NodeSorterApplicable {
+    pub fn new(sorter: NodeSorter, reverse: bool) -> Self {
+        Self { sorter, reverse }
+    }
+
+    fn compare(&self, a: &Node, b: &Node) -> Ordering {
+        if self.reverse {
+            self.sorter.compare(b, a)
+        } else {
+            self.sorter.compare(a, b)
+        }
+    }
+}
+
 #[derive(Debug, Clone, Eq, PartialEq, Serialize, Deserialize)]
 pub struct NodeFilterFromInput {
     filter: NodeFilter,
@@ -383,14 +793,88 @@ pub struct NodeFilterFromInput {
     case_sensitive: bool,
 }
 
+/// The filters and sorters to explore the directories with by default.
+#[derive(Debug, Default, Clone, Eq, PartialEq, Serialize, Deserialize)]
+pub struct DefaultExplorerConfig {
+    #[serde(default)]
+    pub filters: Vec<NodeFilterApplicable>,
+    #[serde(default)]
+    pub sorters: Vec<NodeSorterApplicable>,
+}
+
 #[derive(Debug, Default, Clone, Eq, PartialEq, Serialize, Deserialize)]
 pub struct ExplorerConfig {
     filters: Vec<NodeFilterApplicable>,
+    #[serde(default)]
+    sorters: Vec<NodeSorterApplicable>,
+    #[serde(default)]
+    directories_first: bool,
+    #[serde(default)]
+    max_nodes: Option<usize>,
+    #[serde(default)]
+    symlink_resolution: SymlinkResolution,
+    #[serde(default)]
+    mime_category_filter: Option<NodeFilterApplicable>,
+    #[serde(default)]
+    explore_symlinks_as_dirs: bool,
 }
 
 impl ExplorerConfig {
     pub fn apply(&self, node: &Node) -> bool {
-        self.filters.iter().all(|f| f.apply(node))
+        self.filters
+            .iter()
+            .chain(self.mime_category_filter.iter())
+            .all(|f| f.apply(node))
+    }
+
+    /// The filter managed by `ShowOnlyMimeCategory`, applied along with the
+    /// other filters.
+    pub fn mime_category_filter(&self) -> Option<&NodeFilterApplicable> {
+        self.mime_category_filter.as_ref()
+    }
+
+    /// The filters applied while exploring a directory.
+    pub fn filters(&self) -> &Vec<NodeFilterApplicable> {
+        &self.filters
+    }
+
+    /// The sorters applied while exploring a directory, in order of priority.
+    pub fn sorters(&self) -> &Vec<NodeSorterApplicable> {
+        &self.sorters
+    }
+
+    /// Sort the nodes with the sorters. Stable, so without any sorter, the
+    /// order stays the same.
+    pub fn sort(&self, nodes: &mut [Node]) {
+        nodes.sort_by(|a, b| self.compare(a, b));
+    }
+
+    /// Compare the nodes with the sorters, in order of priority.
+    pub fn compare(&self, a: &Node, b: &Node) -> Ordering {
+        self.sorters
+            .iter()
+            .fold(Ordering::Equal, |ord, s| ord.then_with(|| s.compare(a, b)))
+    }
+
+    /// Whether the directories should be listed before the other nodes.
+    pub fn directories_first(&self) -> bool {
+        self.directories_first
+    }
+
+    /// The maximum number of nodes to load while exploring a directory.
+    pub fn max_nodes(&self) -> Option<usize> {
+        self.max_nodes
+    }
+
+    /// Whether the absolute paths of the explored nodes resolve the symlinks.
+    pub fn symlink_resolution(&self) -> SymlinkResolution {
+        self.symlink_resolution
+    }
+
+    /// Whether the symlinks to directories are descended into while exploring
+    /// recursively.
+    pub fn explore_symlinks_as_dirs(&self) -> bool {
+        self.explore_symlinks_as_dirs
     }
 }
 
@@ -401,15 +885,41 @@ pub enum ExternalMsg {
     /// Once exploration is done, it will auto `Refresh` the state.
     Explore,
 
+    /// Explore the present working directory and its subdirectories up to the
+    /// given depth into a flat list, like `find`. The direct children are at
+    /// depth 1, so `max_depth` must be at least 1. `Back` exits this view by
+    /// exploring the directory normally.
+    ///
+    /// Example: `ExploreRecursive: {max_depth: 2}`
+    ExploreRecursive { max_depth: Option<usize> },
+
     /// Refresh the app state (uncluding UI).
     /// But it will not re-explore the directory if the working directory is the same.
     /// If there is some change in the working directory and you want to re-explore it,
     /// use the `Explore` message instead.
     Refresh,
 
+    /// Clear all the cached directory buffers and checksums, drop the selected
+    /// nodes that no longer exist, and explore the present working directory
+    /// again.
+    /// Helpful when the state on the disk diverges badly from what xplr knows.
+    HardRefresh,
+
+    /// Start a batch of messages. Until `EndBatch` is received, the handlers
+    /// will not ask for a refresh.
+    /// Helpful to avoid redundant refreshes while moving the focus repeatedly.
+    BeginBatch,
+
+    /// End the current batch of messages and refresh once.
+    EndBatch,
+
     /// Clears the screen.
     ClearScreen,
 
+    /// Clears the screen, and then refreshes the app state (including UI) so that
+    /// it gets fully repainted.
+    ClearScreenAndRefresh,
+
     /// Focus next node.
     FocusNext,
 
@@ -434,13 +944,50 @@ pub enum ExternalMsg {
     /// the input buffer.
     FocusPreviousByRelativeIndexFromInput,
 
+    /// Focus on the node one page down, where a page is the number of nodes
+    /// that fit in the list.
+    FocusNextByPage,
+
+    /// Focus on the node one page up, where a page is the number of nodes
+    /// that fit in the list.
+    FocusPreviousByPage,
+
+    /// Focus on a random node. The randomness is not cryptographically secure.
+    FocusRandom,
+
+    /// Focus on the next directory, wrapping around once.
+    FocusNextDirectory,
+
+    /// Focus on the previous directory, wrapping around once.
+    FocusPreviousDirectory,
+
+    /// Focus on the next file, wrapping around once.
+    FocusNextFile,
+
+    /// Focus on the previous file, wrapping around once.
+    FocusPreviousFile,
+
+    /// Compute the SHA-256 checksum of the focused file, remember it, and log it.
+    ComputeFocusedChecksum,
+
+    /// Focus on the next node with the same checksum as the focused one,
+    /// wrapping around once. Only the already computed checksums are compared,
+    /// and only while the files keep the same size and modification time.
+    FocusNextDuplicate,
+
+    /// Focus on the next node that isn't selected, wrapping around once.
+    FocusNextUnselected,
+
+    /// Focus on the previous node that isn't selected, wrapping around once.
+    FocusPreviousUnselected,
+
     /// Focus on the first node.
     FocusFirst,
 
     /// Focus on the last node.
     FocusLast,
 
-    /// Focus on the given path.
+    /// Focus on the given path. A `file://` URI is also accepted.
     ///
     /// Example: `FocusPath: /tmp`
     FocusPath(String),
@@ -448,6 +995,43 @@ pub enum ExternalMsg {
     /// Focus on the path read from input buffer.
     FocusPathFromInput,
 
+    /// Focus on the first node in the present working directory whose relative
+    /// path matches the glob, without filtering anything. Only `*` and `?` are
+    /// supported.
+    ///
+    /// Example: `FocusByGlob: "*.png"`
+    FocusByGlob(String),
+
+    /// Focus on the first node matching the glob read from the input buffer.
+    FocusByGlobFromInput,
+
+    /// Change the directory to the parent of the given path, explore it, and
+    /// focus on the path.
+    /// Exploration happens asynchronously, so the focus is applied only after
+    /// the explored directory gets added. Until then, the focus stays wherever
+    /// the cached buffer (if any) has it.
+    ///
+    /// Example: `ExploreAndFocus: /tmp/foo.txt`
+    ExploreAndFocus(String),
+
+    /// Reveal the given path: canonicalize it, change directory to its parent
+    /// and focus on it. The parent gets explored first if it isn't loaded yet.
+    ///
+    /// Example: `FocusByAbsolutePath: /tmp/foo.txt`
+    FocusByAbsolutePath(String),
+
+    /// Focus on the given path, creating it first if it doesn't exist. A path
+    /// ending with `/` is created as a directory, else, as an empty file, along
+    /// with the missing parent directories. Relative paths are relative to the
+    /// present working directory, and nothing gets created outside of it.
+    ///
+    /// Example: `FocusPathOrCreate: src/new/mod.rs`
+    FocusPathOrCreate(String),
+
+    /// Focus on the first selected node that is not in the present working
+    /// directory. Selected paths that no longer exist are skipped.
+    FocusFirstSelectionAcrossDirs,
+
     /// Focus on the absolute `n`th node where `n` is a given value.
     ///
     /// Example: `FocusByIndex: 2`
@@ -456,16 +1040,46 @@ pub enum ExternalMsg {
     /// Focus on the absolute `n`th node where `n` is read from the input buffer.
     FocusByIndexFromInput,
 
+    /// Same as `FocusByIndexFromInput`, but if the input starts with `+` or `-`,
+    /// focus relative to the current focus instead.
+    /// For example, `+2` focuses 2 nodes down, `-2` 2 nodes up, and `2` the 2nd node.
+    FocusByIndexOrRelativeFromInput,
+
     /// Focus on the file by name from the present working directory.
     ///
     /// Example: `FocusByFileName: README.md`
     FocusByFileName(String),
 
-    /// Change the present working directory ($PWD)
+    /// Focus on the first file whose name without the extension matches the
+    /// given value. Exact matches are preferred over case-insensitive ones.
+    ///
+    /// Example: `FocusByStem: report`
+    FocusByStem(String),
+
+    /// Focus on the next node with the given full extension (e.g. `tar.gz`),
+    /// wrapping around once. The comparison is case-insensitive.
+    ///
+    /// Example: `FocusNextByFullExtension: tar.gz`
+    FocusNextByFullExtension(String),
+
+    /// Focus on the next node at the given depth, wrapping around once.
+    /// The depth is the number of components in the relative path, e.g. `a`
+    /// is at depth 1 and `a/b` is at depth 2. Helpful after `ExploreRecursive`.
+    ///
+    /// Example: `FocusNextAtDepth: 2`
+    FocusNextAtDepth(usize),
+
+    /// Change the present working directory ($PWD). A relative path is
+    /// relative to the present working directory. A `file://` URI is also
+    /// accepted.
     ///
     /// Example: `ChangeDirectory: /tmp`
     ChangeDirectory(String),
 
+    /// Go to the previously visited directory, like `cd -`.
+    /// Sending it twice brings you back where you started.
+    ToggleLastDirectory,
+
     /// Enter into the currently focused path if it's a directory.
     Enter,
 
@@ -491,12 +1105,32 @@ pub enum ExternalMsg {
     /// Reset the input buffer back to null. It will not show in the UI.
     ResetInputBuffer,
 
+    /// Clear the input buffer, keeping it as an empty string. Unlike
+    /// `ResetInputBuffer`, it will still show in the UI.
+    ClearInputBuffer,
+
+    /// Recall the previous input from the history into the input buffer. The
+    /// inputs are remembered when used, e.g. by `FocusPathFromInput` or
+    /// `AddNodeFilterFromInput`, up to `general.input_history_size`.
+    RecallPreviousInput,
+
+    /// Recall the next input from the history into the input buffer, or clear
+    /// the input buffer after the most recent one.
+    RecallNextInput,
+
     /// Switch input mode.
     /// This will reset the input buffer and call `Refresh` automatically.
     ///
     /// Example: `SwitchMode: default`
     SwitchMode(String),
 
+    /// Switch input mode without resetting the input buffer.
+    /// Useful when the input collected in one mode needs to be acted upon
+    /// in another mode.
+    ///
+    /// Example: `SwitchModeKeepingInput: action`
+    SwitchModeKeepingInput(String),
+
     /// Call a shell command with the given arguments.
     /// Note that the arguments will be shell-escaped.
     /// So to read the variables, the `-c` option of the shell
@@ -506,6 +1140,25 @@ pub enum ExternalMsg {
     /// Example: `Call: {command: bash, args: ["-c", "read -p test"]}`
     Call(Command),
 
+    /// Write the selected paths, one per line, into a temporary file and call a
+    /// shell command like `Call` does. The `{selection_file}` placeholder in the
+    /// arguments gets replaced with the path of the file. Helpful when the
+    /// selection is too large to pass as arguments or environment variables.
+    /// The file is deleted once the command returns, whatever the outcome.
+    /// For the same reason, `XPLR_SELECTION`, `XPLR_SELECTION_ADDED`,
+    /// `XPLR_SELECTION_REMOVED`, `XPLR_RESULT` and `XPLR_APP_YAML` are not
+    /// passed to the command.
+    ///
+    /// Example: `WriteSelectionToTempFileAndCall: {command: xargs, args: ["-a", "{selection_file}", "ls"]}`
+    WriteSelectionToTempFileAndCall(Command),
+
+    /// Like `WriteSelectionToTempFileAndCall`, but with the paths separated by
+    /// NUL characters instead of new lines, for the paths that contain new
+    /// lines.
+    ///
+    /// Example: `WriteNulSeparatedSelectionToTempFileAndCall: {command: xargs, args: ["-0", "-a", "{selection_file}", "ls"]}`
+    WriteNulSeparatedSelectionToTempFileAndCall(Command),
+
     /// Select the focused node.
     Select,
 
@@ -515,9 +1168,75 @@ pub enum ExternalMsg {
     /// Toggle selection on the focused node.
     ToggleSelection,
 
+    /// Select the focused node and focus on the next one.
+    SelectAndFocusNext,
+
+    /// Unselect the focused node and focus on the next one.
+    UnSelectAndFocusNext,
+
+    /// Toggle selection on the focused node and focus on the next one.
+    /// Helpful for selecting a run of consecutive nodes.
+    ToggleSelectionAndFocusNext,
+
+    /// Select every node between the last selected node and the focused node,
+    /// both inclusive. If nothing was selected in the present working directory
+    /// yet, only the focused node gets selected.
+    SelectRangeFromLastFocus,
+
+    /// Remember the focused index in the present working directory as the
+    /// anchor for `SelectToAnchor`. It's forgotten when the directory changes.
+    SetSelectionAnchor,
+
+    /// Forget the anchor set by `SetSelectionAnchor`.
+    ClearSelectionAnchor,
+
+    /// Select every node between the anchor and the focused node, both
+    /// inclusive, keeping the anchor.
+    SelectToAnchor,
+
+    /// Toggle selection on every node in the present working directory whose
+    /// mime essence starts with the given value. The selected ones get unselected,
+    /// and the rest get selected.
+    ///
+    /// Example: `ToggleSelectionByMime: image/`
+    ToggleSelectionByMime(String),
+
+    /// Replace the whole selection with the given absolute paths. The paths
+    /// that don't exist are skipped with a warning.
+    ///
+    /// Example: `SetSelection: [/tmp/foo, /tmp/bar]`
+    SetSelection(Vec<String>),
+
     /// Clear the selection
     ClearSelection,
 
+    /// Unselect the nodes in the present working directory, keeping the ones
+    /// selected elsewhere.
+    ClearSelectionInCurrentDirectory,
+
+    /// Rename the selected nodes in order, within their directories, using a
+    /// template. The placeholders are `{n}` for the sequence number starting
+    /// at 1, optionally zero padded like `{n:03}`, `{name}` for the file name,
+    /// `{stem}` for the name without the extension, and `{ext}` for the
+    /// extension. An empty `{ext}` takes the dot before it along. Nothing is
+    /// renamed if any of the new names clash with each other or with an
+    /// existing node.
+    ///
+    /// Needs a `ConfirmPending` if `general.confirm_destructive` is set.
+    ///
+    /// Example: `RenameSelectionWithTemplate: "{n:03}_{stem}.{ext}"`
+    RenameSelectionWithTemplate(String),
+
+    /// Carry out the destructive operation waiting for a confirmation, if any.
+    ///
+    /// Example: `ConfirmPending`
+    ConfirmPending,
+
+    /// Discard the destructive operation waiting for a confirmation, if any.
+    ///
+    /// Example: `CancelPending`
+    CancelPending,
+
     /// Add a filter to explude nodes while exploring directories.
     ///
     /// Example: `AddNodeFilter: {filter: RelativePathDoesStartWith, input: foo}`
@@ -533,19 +1252,67 @@ pub enum ExternalMsg {
     /// Example: `ToggleNodeFilter: {filter: RelativePathDoesStartWith, input: foo}`
     ToggleNodeFilter(NodeFilterApplicable),
 
+    /// Show the hidden nodes if any of them are being filtered out, else, hide
+    /// them again. Hidden are the dotfiles and the nodes matching
+    /// `general.hidden_file_patterns`.
+    ToggleHidden,
+
+    /// Toggle a filter hiding the nodes with the same extension as the focused
+    /// node, and explore again. e.g. hide all the `.o` files at once.
+    ToggleFilterByFocusedExtension,
+
     /// Add a node filter reading the input from the buffer.
     ///
     /// Example: `AddNodeFilterFromInput: {filter: RelativePathDoesStartWith}`
     AddNodeFilterFromInput(NodeFilterFromInput),
 
+    /// Log how many nodes in the present working directory would pass the
+    /// filter built using the input read from the buffer, without applying it.
+    ///
+    /// Example: `PreviewFilterFromInput: {filter: RelativePathDoesContain}`
+    PreviewFilterFromInput(NodeFilterFromInput),
+
     /// Reset the node filters back to the default configuration.
     ResetNodeFilters,
 
+    /// Show only the nodes with the given MIME category, i.e. the MIME essence
+    /// starting with `category/`, and explore again. It replaces the category
+    /// shown before, instead of adding up with the other filters.
+    ///
+    /// Example: `ShowOnlyMimeCategory: image`
+    ShowOnlyMimeCategory(String),
+
+    /// Stop filtering by the MIME category set by `ShowOnlyMimeCategory`, and
+    /// explore again.
+    ShowAllMimeCategories,
+
+    /// Sort the nodes in the present working directory. Unless
+    /// `general.focus_follows_sort` is disabled, the focus moves along with the
+    /// focused node. The order is lost when the directory is explored again.
+    ///
+    /// Example: `SortNodes: {sorter: ByRelativePath, reverse: true}`
+    SortNodes(NodeSorterApplicable),
+
+    /// Swap the focused node with the next one, keeping the focus on it.
+    /// It only reorders the view, and the order is lost when the directory is
+    /// explored again.
+    SwapFocusWithNext,
+
+    /// Swap the focused node with the previous one, keeping the focus on it.
+    /// It only reorders the view, and the order is lost when the directory is
+    /// explored again.
+    SwapFocusWithPrevious,
+
     /// Log information message. Stored in `$XPLR_LOGS`.
     ///
     /// Example: `LogInfo: launching satellite`
     LogInfo(String),
 
+    /// Log a warning message. Stored in `$XPLR_LOGS`.
+    ///
+    /// Example: `LogWarning: satellite is heating up`
+    LogWarning(String),
+
     /// Log a success message. Stored in `$XPLR_LOGS`.
     ///
     /// Example: `LogSuccess: satellite reached destination`. Stored in `$XPLR_LOGS`
@@ -559,14 +1326,64 @@ pub enum ExternalMsg {
     /// Print selected paths if it's not empty, else, print the focused node's path.
     PrintResultAndQuit,
 
+    /// Same as `PrintResultAndQuit`, but the paths are made relative to the given
+    /// base directory. Paths that can't be made relative (e.g. they live on a
+    /// different root) are printed as absolute paths.
+    ///
+    /// Example: `PrintRelativePathAndQuit: /home/user/repo`
+    PrintRelativePathAndQuit(String),
+
     /// Print the state of application in YAML format. Helpful for debugging or generating
     /// the default configuration file.
     PrintAppStateAndQuit,
 
-    /// Write the application state to a file, without quitting. Also helpful for debugging.
-    Debug(String),
+    /// Print the focused node with its size, modification time and permissions
+    /// in JSON format and quit. Logs an error and keeps running if nothing is
+    /// focused.
+    PrintFocusedMetadataAndQuit,
 
-    /// Terminate the application with a non-zero return code.
+    /// Print the present working directory and quit, even if a file is focused.
+    /// Helpful for using xplr as a directory picker, e.g. `cd "$(xplr)"`.
+    PrintPwdAndQuit,
+
+    /// Print the selection stats, i.e. the count, the total size in bytes and
+    /// the number of directories, files and symlinks, as JSON and quit. Zeros
+    /// are printed if nothing is selected.
+    PrintSelectionStatsAndQuit,
+
+    /// Print the focused path and the selected paths, kept apart, as JSON and
+    /// quit, e.g. `{"focused": "/tmp/a", "selection": ["/tmp/b"]}`. Unlike
+    /// `PrintResultAndQuit`, the focus isn't a fallback for the selection.
+    PrintFocusedAndSelectionAndQuit,
+
+    /// Write the application state to a file, without quitting. Also helpful for debugging.
+    Debug(String),
+
+    /// Check the application state for inconsistencies, e.g. a focus out of
+    /// range, and log each of them. Helpful for reporting bugs.
+    SelfCheckAndLog,
+
+    /// Log which binding of the current mode would handle the given key, and
+    /// the messages it would send. Helpful for debugging shadowed bindings.
+    ///
+    /// Example: `ExplainKey: ctrl-c`
+    ExplainKey(String),
+
+    /// Wait for the given milliseconds before processing the next message.
+    /// Helpful for recording demos. It's capped at 10 seconds.
+    ///
+    /// Example: `Sleep: 500`
+    Sleep(u64),
+
+    /// Go back to the state xplr was started in, without restarting it. It
+    /// changes back to the initial directory and mode, clears the selection,
+    /// the input buffer, the logs and any pending focus, batch or
+    /// confirmation, resets the filters and sorters to the config default, and
+    /// explores again. The cached directory buffers and the input history are
+    /// kept. Helpful when xplr is used as a picker over and over.
+    Reset,
+
+    /// Terminate the application with a non-zero return code.
     Terminate,
 }
 
@@ -584,15 +1401,40 @@ pub struct Command {
     pub args: Vec<String>,
 }
 
+impl Command {
+    /// Replace the `{selection_file}` placeholder in the arguments with the given path.
+    pub fn with_selection_file(&self, path: &str) -> Self {
+        Self {
+            command: self.command.clone(),
+            args: self
+                .args
+                .iter()
+                .map(|a| a.replace("{selection_file}", path))
+                .collect(),
+        }
+    }
+}
+
 #[derive(Debug, Clone, Eq, PartialEq, Serialize, Deserialize)]
 pub enum MsgOut {
     Explore,
+    ExploreRecursive(Option<usize>),
     Refresh,
     ClearScreen,
     PrintResultAndQuit,
+    PrintRelativePathAndQuit(String),
     PrintAppStateAndQuit,
+    PrintFocusedMetadataAndQuit,
+    PrintPwdAndQuit,
+    PrintSelectionStatsAndQuit,
+    PrintFocusedAndSelectionAndQuit,
     Debug(String),
+    Sleep(Duration),
     Call(Command),
+    CallWithSelectionFile {
+        command: Command,
+        nul_separated: bool,
+    },
 }
 
 #[derive(Debug, Clone, Eq, PartialEq, Serialize, Deserialize)]
@@ -635,6 +1477,7 @@ impl PartialOrd for Task {
 #[serde(rename_all = "UPPERCASE")]
 pub enum LogLevel {
     Info,
+    Warning,
     Success,
     Error,
 }
@@ -660,6 +1503,7 @@ impl std::fmt::Display for Log {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         let level_str = match self.level {
             LogLevel::Info => "INFO   ",
+            LogLevel::Warning => "WARNING",
             LogLevel::Success => "SUCCESS",
             LogLevel::Error => "ERROR  ",
         };
@@ -673,13 +1517,347 @@ pub enum HelpMenuLine {
     Paragraph(String),
 }
 
+fn dotfile_filter() -> NodeFilterApplicable {
+    NodeFilterApplicable::new(
+        NodeFilter::RelativePathDoesNotStartWith,
+        ".".into(),
+        Default::default(),
+    )
+}
+
+/// Get the explorer config as per the config, before any change by the user.
+fn default_explorer_config(config: &Config) -> ExplorerConfig {
+    ExplorerConfig {
+        filters: default_filters(config),
+        sorters: config
+            .general
+            .default_explorer_config
+            .as_ref()
+            .map(|c| c.sorters.clone())
+            .unwrap_or_default(),
+        directories_first: config.general.directories_first,
+        max_nodes: config.general.max_nodes,
+        symlink_resolution: config.general.symlink_resolution,
+        mime_category_filter: None,
+        explore_symlinks_as_dirs: config.general.explore_symlinks_as_dirs,
+    }
+}
+
+/// Get the filters hiding the nodes matching `general.hidden_file_patterns`.
+fn hidden_pattern_filters(config: &Config) -> Vec<NodeFilterApplicable> {
+    config
+        .general
+        .hidden_file_patterns
+        .iter()
+        .map(|p| NodeFilterApplicable::new(NodeFilter::RelativePathDoesNotMatch, p.clone(), true))
+        .collect()
+}
+
+/// Get the filters to explore with by default. Unless configured otherwise,
+/// only the hidden nodes are filtered out, if they aren't to be shown.
+fn default_filters(config: &Config) -> Vec<NodeFilterApplicable> {
+    let mut filters = if let Some(default) = config.general.default_explorer_config.as_ref() {
+        default.filters.clone()
+    } else if config.general.show_hidden {
+        vec![]
+    } else {
+        vec![dotfile_filter()]
+    };
+
+    if !config.general.show_hidden {
+        for filter in hidden_pattern_filters(config) {
+            if !filters.contains(&filter) {
+                filters.push(filter);
+            }
+        }
+    };
+    filters
+}
+
+/// Refuse the globs `glob_matches` can't handle, rather than matching them
+/// literally.
+fn validate_glob(pattern: &str) -> std::result::Result<(), String> {
+    if pattern.is_empty() {
+        Err("invalid glob: it's empty".into())
+    } else if pattern.contains(|c| "[]{}".contains(c)) {
+        Err(format!(
+            "invalid glob {}: only * and ? are supported",
+            pattern
+        ))
+    } else {
+        Ok(())
+    }
+}
+
+/// Render the name of the nth node renamed with the template. See
+/// `ExternalMsg::RenameSelectionWithTemplate`.
+fn render_rename_template(
+    template: &str,
+    n: usize,
+    name: &str,
+) -> std::result::Result<String, String> {
+    let path = Path::new(name);
+    let stem = path
+        .file_stem()
+        .map(|s| s.to_string_lossy().to_string())
+        .unwrap_or_default();
+    let ext = path
+        .extension()
+        .map(|e| e.to_string_lossy().to_string())
+        .unwrap_or_default();
+
+    let mut rendered = String::new();
+    let mut rest = template;
+    while let Some(start) = rest.find('{') {
+        rendered.push_str(&rest[..start]);
+        let end = rest[start..]
+            .find('}')
+            .map(|e| start + e)
+            .ok_or_else(|| format!("unclosed placeholder in {}", template))?;
+
+        let placeholder = &rest[start + 1..end];
+        let (key, spec) = match placeholder.find(':') {
+            Some(i) => (&placeholder[..i], Some(&placeholder[i + 1..])),
+            None => (placeholder, None),
+        };
+
+        match (key, spec) {
+            ("n", None) => rendered.push_str(&n.to_string()),
+            ("n", Some(spec)) => {
+                let width = spec
+                    .parse::<usize>()
+                    .map_err(|_| format!("invalid width {} in {}", spec, template))?;
+                if spec.starts_with('0') {
+                    rendered.push_str(&format!("{:0width$}", n, width = width));
+                } else {
+                    rendered.push_str(&format!("{:width$}", n, width = width));
+                }
+            }
+            ("name", None) => rendered.push_str(name),
+            ("stem", None) => rendered.push_str(&stem),
+            ("ext", None) if ext.is_empty() => {
+                // No `name.` for the nodes without an extension.
+                if rendered.ends_with('.') {
+                    rendered.pop();
+                };
+            }
+            ("ext", None) => rendered.push_str(&ext),
+            _ => {
+                return Err(format!(
+                    "unknown placeholder {{{}}} in {}",
+                    placeholder, template
+                ))
+            }
+        };
+        rest = &rest[end + 1..];
+    }
+    rendered.push_str(rest);
+
+    if rendered.is_empty() || rendered.contains('/') || rendered == "." || rendered == ".." {
+        Err(format!("invalid name {:?}", rendered))
+    } else {
+        Ok(rendered)
+    }
+}
+
+/// Resolve the `.` and `..` components without touching the disk.
+fn normalize_path(path: &Path) -> PathBuf {
+    let mut normalized = PathBuf::new();
+    for component in path.components() {
+        match component {
+            Component::CurDir => {}
+            Component::ParentDir => {
+                normalized.pop();
+            }
+            c => normalized.push(c),
+        }
+    }
+    normalized
+}
+
+/// Turn a `file://` URI, e.g. pasted from a file manager, into a path. Anything
+/// that isn't a URI is returned as is, and the other schemes are refused.
+pub fn path_from_uri(input: &str) -> std::result::Result<String, String> {
+    let (scheme, rest) = match input.find("://") {
+        Some(i) => (&input[..i], &input[i + 3..]),
+        None => return Ok(input.to_string()),
+    };
+
+    if scheme != "file" {
+        return Err(format!("unsupported URI scheme {} in {}", scheme, input));
+    };
+
+    // Skip the host, e.g. `localhost` in `file://localhost/tmp`.
+    let path = rest.find('/').map(|i| &rest[i..]).unwrap_or("/");
+
+    let bytes = path.as_bytes();
+    let mut decoded = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        let hex = if bytes[i] == b'%' && i + 2 < bytes.len() {
+            std::str::from_utf8(&bytes[i + 1..i + 3])
+                .ok()
+                .and_then(|h| u8::from_str_radix(h, 16).ok())
+        } else {
+            None
+        };
+
+        if let Some(byte) = hex {
+            decoded.push(byte);
+            i += 3;
+        } else {
+            decoded.push(bytes[i]);
+            i += 1;
+        };
+    }
+
+    Ok(String::from_utf8_lossy(&decoded).to_string())
+}
+
+/// Get the SHA-256 checksum of the file in hex, reading it in a streaming
+/// fashion.
+#[cfg(feature = "checksum")]
+pub fn file_checksum(path: &str) -> io::Result<String> {
+    use sha2::{Digest, Sha256};
+
+    let mut hasher = Sha256::new();
+    io::copy(&mut fs::File::open(path)?, &mut hasher)?;
+    Ok(hasher
+        .result()
+        .iter()
+        .map(|b| format!("{:02x}", b))
+        .collect())
+}
+
+#[cfg(not(feature = "checksum"))]
+pub fn file_checksum(_path: &str) -> io::Result<String> {
+    Err(io::Error::new(
+        io::ErrorKind::Other,
+        "xplr was built without the checksum feature",
+    ))
+}
+
+/// A computed checksum, along with the size and the modification time of the
+/// file at that moment, to tell when it's outdated.
+#[derive(Debug, Clone, Eq, PartialEq, Serialize, Deserialize)]
+pub struct Checksum {
+    pub digest: String,
+    pub size: u64,
+    pub modified: Option<DateTime<Utc>>,
+}
+
+impl Checksum {
+    pub fn of(path: &str) -> io::Result<Self> {
+        let (size, modified) = Self::stat(path)?;
+        let digest = file_checksum(path)?;
+        Ok(Self {
+            digest,
+            size,
+            modified,
+        })
+    }
+
+    /// Whether the file still has the same size and modification time.
+    pub fn is_fresh(&self, path: &str) -> bool {
+        Self::stat(path)
+            .map(|(size, modified)| size == self.size && modified == self.modified)
+            .unwrap_or(false)
+    }
+
+    fn stat(path: &str) -> io::Result<(u64, Option<DateTime<Utc>>)> {
+        let metadata = fs::metadata(path)?;
+        let modified = metadata.modified().ok().map(DateTime::<Utc>::from);
+        Ok((metadata.len(), modified))
+    }
+}
+
+/// Compare two selections by absolute path. Returns (added, removed), in the
+/// order they appear in the current and the previous selection respectively.
+pub fn selection_diff(previous: &[String], current: &[String]) -> (Vec<String>, Vec<String>) {
+    let previous_set = previous.iter().collect::<HashSet<&String>>();
+    let current_set = current.iter().collect::<HashSet<&String>>();
+
+    let added = current
+        .iter()
+        .filter(|p| !previous_set.contains(p))
+        .cloned()
+        .collect();
+
+    let removed = previous
+        .iter()
+        .filter(|p| !current_set.contains(p))
+        .cloned()
+        .collect();
+
+    (added, removed)
+}
+
+/// Get the path relative to the given base directory, the way `pathdiff` does it.
+/// Returns `None` when there's no relative path between the two, e.g. when they
+/// are on different roots.
+pub fn relative_path(path: &Path, base: &Path) -> Option<PathBuf> {
+    if path.is_absolute() != base.is_absolute() {
+        return None;
+    };
+
+    let mut path_comps = path.components();
+    let mut base_comps = base.components();
+
+    if path.is_absolute() && path_comps.next() != base_comps.next() {
+        return None;
+    };
+
+    let mut comps: Vec<Component> = vec![];
+    loop {
+        match (path_comps.next(), base_comps.next()) {
+            (None, None) => break,
+            (Some(p), None) => {
+                comps.push(p);
+                comps.extend(path_comps.by_ref());
+                break;
+            }
+            (None, _) => comps.push(Component::ParentDir),
+            (Some(p), Some(b)) if comps.is_empty() && p == b => (),
+            (Some(p), Some(Component::CurDir)) => comps.push(p),
+            (Some(_), Some(Component::ParentDir)) => return None,
+            (Some(p), Some(_)) => {
+                comps.push(Component::ParentDir);
+                comps.extend(base_comps.by_ref().map(|_| Component::ParentDir));
+                comps.push(p);
+                comps.extend(path_comps.by_ref());
+                break;
+            }
+        }
+    }
+
+    if comps.is_empty() {
+        Some(PathBuf::from("."))
+    } else {
+        Some(comps.iter().map(|c| c.as_os_str()).collect())
+    }
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct App {
     config: Config,
     pwd: String,
+    #[serde(default)]
+    last_pwd: Option<String>,
     directory_buffers: HashMap<String, DirectoryBuffer>,
+    #[serde(default)]
+    last_focus: HashMap<String, usize>,
+    #[serde(default)]
+    pending_focus: Option<(String, String)>,
+    #[serde(default)]
+    entered_directory: Option<String>,
+    #[serde(default)]
+    explored_directory: Option<String>,
     tasks: BinaryHeap<Task>,
     selection: Vec<Node>,
+    #[serde(default)]
+    last_selected_index: Option<usize>,
+    #[serde(default)]
+    selection_anchor: Option<usize>,
     msg_out: VecDeque<MsgOut>,
     mode: Mode,
     input_buffer: Option<String>,
@@ -688,6 +1866,28 @@ pub struct App {
     pipe: Pipe,
     explorer_config: ExplorerConfig,
     logs: Vec<Log>,
+    #[serde(default)]
+    layout: ScreenLayout,
+    #[serde(default)]
+    random_seed: u64,
+    #[serde(default)]
+    suppress_refresh: bool,
+    #[serde(default)]
+    exploring_recursively: bool,
+    #[serde(default)]
+    animations: BTreeMap<String, usize>,
+    #[serde(default)]
+    checksums: HashMap<String, Checksum>,
+    #[serde(default)]
+    input_history: VecDeque<String>,
+    #[serde(default)]
+    input_history_index: Option<usize>,
+    #[serde(default)]
+    initial_pwd: String,
+    #[serde(default)]
+    initial_mode: Mode,
+    #[serde(default)]
+    pending_confirmation: Option<ExternalMsg>,
 }
 
 impl App {
@@ -725,52 +1925,145 @@ impl App {
                 VERSION,
             )
         } else {
-            let mode = config
-                .modes
-                .get(&"default".to_string())
-                .map(|k| k.to_owned())
-                .unwrap_or_default();
-
-            let pid = std::process::id();
-            let session_path = dirs::runtime_dir()
-                .unwrap_or("/tmp".into())
-                .join("xplr")
-                .join("session")
-                .join(&pid.to_string())
-                .to_string_lossy()
-                .to_string();
-
-            let mut explorer_config = ExplorerConfig::default();
-            if !config.general.show_hidden {
-                explorer_config.filters.push(NodeFilterApplicable::new(
-                    NodeFilter::RelativePathDoesNotStartWith,
-                    ".".into(),
-                    Default::default(),
-                ));
-            }
+            Ok(Self::new(config, pwd))
+        }
+    }
 
-            Ok(Self {
-                config,
-                pwd,
-                directory_buffers: Default::default(),
-                tasks: Default::default(),
-                selection: Default::default(),
-                msg_out: Default::default(),
-                mode,
-                input_buffer: Default::default(),
-                pid,
-                session_path: session_path.clone(),
-                pipe: Pipe::from_session_path(&session_path),
-                explorer_config,
-                logs: Default::default(),
+    /// Create the app with the given configuration and present working directory.
+    pub fn new(config: Config, pwd: String) -> Self {
+        let mut logs = config
+            .validate()
+            .into_iter()
+            .map(|p| Log::new(LogLevel::Error, format!("invalid config: {}", p)))
+            .collect::<Vec<Log>>();
+        let start_mode = config
+            .general
+            .start_mode
+            .clone()
+            .unwrap_or_else(|| "default".into());
+
+        let mode = config
+            .modes
+            .get(&start_mode)
+            .or_else(|| {
+                logs.push(Log::new(
+                    LogLevel::Error,
+                    format!(
+                        "start mode {} not found, falling back to default",
+                        start_mode
+                    ),
+                ));
+                config.modes.get(&"default".to_string())
             })
+            .map(|k| k.to_owned())
+            .unwrap_or_default();
+
+        let pid = std::process::id();
+        let session_path = dirs::runtime_dir()
+            .unwrap_or("/tmp".into())
+            .join("xplr")
+            .join("session")
+            .join(&pid.to_string())
+            .to_string_lossy()
+            .to_string();
+
+        let explorer_config = default_explorer_config(&config);
+
+        Self {
+            config,
+            pwd: pwd.clone(),
+            last_pwd: Default::default(),
+            directory_buffers: Default::default(),
+            last_focus: Default::default(),
+            pending_focus: Default::default(),
+            entered_directory: Default::default(),
+            explored_directory: Default::default(),
+            tasks: Default::default(),
+            selection: Default::default(),
+            last_selected_index: Default::default(),
+            selection_anchor: Default::default(),
+            msg_out: Default::default(),
+            mode: mode.clone(),
+            input_buffer: Default::default(),
+            pid,
+            session_path: session_path.clone(),
+            pipe: Pipe::from_session_path(&session_path),
+            explorer_config,
+            logs,
+            layout: Default::default(),
+            random_seed: Utc::now().timestamp_nanos() as u64,
+            suppress_refresh: false,
+            exploring_recursively: false,
+            animations: Default::default(),
+            checksums: Default::default(),
+            input_history: Default::default(),
+            input_history_index: Default::default(),
+            initial_pwd: pwd,
+            initial_mode: mode,
+            pending_confirmation: Default::default(),
         }
     }
 
+    /// Register an animation (e.g. a spinner) to be advanced by a frame on
+    /// every tick. The ticks are sent only if `general.tick_interval` is set.
+    pub fn register_animation(mut self, name: &str) -> Self {
+        self.animations.entry(name.into()).or_default();
+        self
+    }
+
+    /// Stop advancing the animation.
+    pub fn unregister_animation(mut self, name: &str) -> Self {
+        self.animations.remove(name);
+        self
+    }
+
+    /// Get a reference to the checksums computed so far, by absolute path.
+    pub fn checksums(&self) -> &HashMap<String, Checksum> {
+        &self.checksums
+    }
+
+    /// Get the current frame of the animation, if it's registered.
+    pub fn animation_frame(&self, name: &str) -> Option<usize> {
+        self.animations.get(name).cloned()
+    }
+
+    /// Load the app from the state dumped by `PrintAppStateAndQuit` or `Debug`,
+    /// without touching the file system, the arguments or the pipes. Helpful
+    /// for reproducing a reported bug.
+    pub fn from_yaml_str(state: &str) -> Result<Self> {
+        Ok(serde_yaml::from_str(state)?)
+    }
+
+    /// Use the given seed for the random operations, e.g. `FocusRandom`.
+    pub fn with_random_seed(mut self, seed: u64) -> Self {
+        self.random_seed = seed;
+        self
+    }
+
     pub fn focused_node(&self) -> Option<&Node> {
         self.directory_buffer().and_then(|d| d.focused_node())
     }
 
+    /// Get the name of the node to focus on while exploring the present
+    /// working directory. A pending focus wins over the focused node.
+    pub fn explore_focus(&self) -> Option<String> {
+        self.pending_focus
+            .as_ref()
+            .filter(|(dir, _)| dir == &self.pwd)
+            .map(|(_, name)| name.clone())
+            .or_else(|| self.focused_node().map(|n| n.relative_path.clone()))
+    }
+
+    /// Get the node at the given index in the present working directory.
+    pub fn node_at(&self, index: usize) -> Option<&Node> {
+        self.directory_buffer().and_then(|d| d.nodes.get(index))
+    }
+
+    /// Get the focus of the present working directory, if it's loaded.
+    pub fn focused_index(&self) -> Option<usize> {
+        self.directory_buffer().map(|d| d.focus)
+    }
+
     pub fn enqueue(mut self, task: Task) -> Self {
         self.tasks.push(task);
         self
@@ -778,27 +2071,127 @@ impl App {
 
     pub fn possibly_mutate(mut self) -> Result<Self> {
         if let Some(task) = self.tasks.pop() {
+            let queued = self.msg_out.len();
+            let mode = self.mode.name.clone();
             match task.msg {
                 MsgIn::Internal(msg) => self.handle_internal(msg),
                 MsgIn::External(msg) => self.handle_external(msg, task.key),
             }
+            .map(|a| a.remember_focus())
+            .map(|a| a.suppress_batched_refresh(queued))
+            .and_then(|a| {
+                if a.mode.name != mode {
+                    a.notify_mode_change()
+                } else {
+                    Ok(a)
+                }
+            })
+        } else {
+            Ok(self)
+        }
+    }
+
+    /// Write the active mode to `mode_out`, for the status bars to follow.
+    /// Best effort, a failure is only logged.
+    fn notify_mode_change(self) -> Result<Self> {
+        if let Err(e) = self.pipe.write_mode_out(&self.mode.name) {
+            let message = format!("failed to write {}: {}", &self.pipe.mode_out, e);
+            self.log_error(message)
         } else {
             Ok(self)
         }
     }
 
+    /// Drop the refreshes requested after the first `from` queued messages.
+    /// Returns whether any was dropped.
+    fn drop_refreshes_after(&mut self, from: usize) -> bool {
+        let mut index = 0;
+        let mut dropped = false;
+        self.msg_out.retain(|m| {
+            index += 1;
+            if index > from && m == &MsgOut::Refresh {
+                dropped = true;
+                false
+            } else {
+                true
+            }
+        });
+        dropped
+    }
+
+    /// While in a batch, drop the refreshes requested after the first `from`
+    /// queued messages.
+    fn suppress_batched_refresh(mut self, from: usize) -> Self {
+        if self.suppress_refresh {
+            self.drop_refreshes_after(from);
+        };
+        self
+    }
+
+    /// Run the handler followed by `FocusNext`, refreshing only once.
+    fn and_focus_next<F>(self, handler: F) -> Result<Self>
+    where
+        F: FnOnce(Self) -> Result<Self>,
+    {
+        let queued = self.msg_out.len();
+        let mut app = handler(self)?.focus_next()?;
+        if app.drop_refreshes_after(queued) {
+            app.msg_out.push_back(MsgOut::Refresh);
+        };
+        Ok(app)
+    }
+
+    fn remember_focus(mut self) -> Self {
+        if let Some(focus) = self.directory_buffer().map(|d| d.focus) {
+            self.last_focus.insert(self.pwd.clone(), focus);
+        };
+        self
+    }
+
     fn handle_internal(self, msg: InternalMsg) -> Result<Self> {
         match msg {
             InternalMsg::AddDirectory(parent, dir) => self.add_directory(parent, dir),
             InternalMsg::HandleKey(key) => self.handle_key(key),
+            InternalMsg::Tick => self.tick(),
+            InternalMsg::SetLayout {
+                rows,
+                cols,
+                list_height,
+            } => self.set_layout(ScreenLayout {
+                rows,
+                cols,
+                list_height,
+            }),
         }
     }
 
     fn handle_external(self, msg: ExternalMsg, key: Option<Key>) -> Result<Self> {
         match msg {
             ExternalMsg::Explore => self.explore(),
+            ExternalMsg::ExploreRecursive { max_depth } => self.explore_recursive(max_depth),
             ExternalMsg::Refresh => self.refresh(),
+            ExternalMsg::HardRefresh => self.hard_refresh(),
+            ExternalMsg::BeginBatch => self.begin_batch(),
+            ExternalMsg::EndBatch => self.end_batch(),
             ExternalMsg::ClearScreen => self.clear_screen(),
+            ExternalMsg::ClearScreenAndRefresh => self.clear_screen_and_refresh(),
+            ExternalMsg::FocusNextByPage => self.focus_next_by_page(),
+            ExternalMsg::FocusPreviousByPage => self.focus_previous_by_page(),
+            ExternalMsg::FocusRandom => self.focus_random(),
+            ExternalMsg::FocusNextDirectory => {
+                self.focus_next_matching(true, "directory", |n| n.is_dir)
+            }
+            ExternalMsg::FocusPreviousDirectory => {
+                self.focus_next_matching(false, "directory", |n| n.is_dir)
+            }
+            ExternalMsg::ComputeFocusedChecksum => self.compute_focused_checksum(),
+            ExternalMsg::FocusNextDuplicate => self.focus_next_duplicate(),
+            ExternalMsg::FocusNextUnselected => self.focus_next_unselected(true),
+            ExternalMsg::FocusPreviousUnselected => self.focus_next_unselected(false),
+            ExternalMsg::FocusNextFile => self.focus_next_matching(true, "file", |n| n.is_file),
+            ExternalMsg::FocusPreviousFile => {
+                self.focus_next_matching(false, "file", |n| n.is_file)
+            }
             ExternalMsg::FocusFirst => self.focus_first(),
             ExternalMsg::FocusLast => self.focus_last(),
             ExternalMsg::FocusPrevious => self.focus_previous(),
@@ -814,58 +2207,147 @@ impl App {
             ExternalMsg::FocusNextByRelativeIndexFromInput => {
                 self.focus_next_by_relative_index_from_input()
             }
-            ExternalMsg::FocusPath(p) => self.focus_path(&p),
+            ExternalMsg::FocusPath(p) => match path_from_uri(&p) {
+                Ok(p) => self.focus_path(&p),
+                Err(e) => self.log_error(e),
+            },
             ExternalMsg::FocusPathFromInput => self.focus_path_from_input(),
+            ExternalMsg::FocusByGlob(g) => self.focus_by_glob(&g),
+            ExternalMsg::FocusByGlobFromInput => self.focus_by_glob_from_input(),
+            ExternalMsg::ExploreAndFocus(p) => self.explore_and_focus(&p),
+            ExternalMsg::FocusByAbsolutePath(p) => self.focus_by_absolute_path(&p),
+            ExternalMsg::FocusPathOrCreate(p) => self.focus_path_or_create(&p),
+            ExternalMsg::FocusFirstSelectionAcrossDirs => self.focus_first_selection_across_dirs(),
             ExternalMsg::FocusByIndex(i) => self.focus_by_index(i),
             ExternalMsg::FocusByIndexFromInput => self.focus_by_index_from_input(),
+            ExternalMsg::FocusByIndexOrRelativeFromInput => {
+                self.focus_by_index_or_relative_from_input()
+            }
             ExternalMsg::FocusByFileName(n) => self.focus_by_file_name(&n),
-            ExternalMsg::ChangeDirectory(dir) => self.change_directory(&dir),
+            ExternalMsg::FocusByStem(n) => self.focus_by_stem(&n),
+            ExternalMsg::FocusNextAtDepth(d) => {
+                self.focus_next_matching(true, &format!("node at depth {}", d), |n| n.depth() == d)
+            }
+            ExternalMsg::FocusNextByFullExtension(e) => {
+                let ext = e.to_lowercase();
+                self.focus_next_matching(true, &format!("node with extension {}", e), |n| {
+                    n.full_extension().to_lowercase() == ext
+                })
+            }
+            ExternalMsg::ChangeDirectory(dir) => match path_from_uri(&dir) {
+                Ok(dir) => self.change_directory(&dir),
+                Err(e) => self.log_error(e),
+            },
+            ExternalMsg::ToggleLastDirectory => self.toggle_last_directory(),
             ExternalMsg::Enter => self.enter(),
             ExternalMsg::Back => self.back(),
             ExternalMsg::BufferInput(input) => self.buffer_input(&input),
             ExternalMsg::BufferInputFromKey => self.buffer_input_from_key(key),
             ExternalMsg::SetInputBuffer(input) => self.set_input_buffer(input),
             ExternalMsg::ResetInputBuffer => self.reset_input_buffer(),
+            ExternalMsg::ClearInputBuffer => self.clear_input_buffer(),
+            ExternalMsg::RecallPreviousInput => self.recall_previous_input(),
+            ExternalMsg::RecallNextInput => self.recall_next_input(),
             ExternalMsg::SwitchMode(mode) => self.switch_mode(&mode),
+            ExternalMsg::SwitchModeKeepingInput(mode) => self.switch_mode_keeping_input(&mode),
             ExternalMsg::Call(cmd) => self.call(cmd),
+            ExternalMsg::WriteSelectionToTempFileAndCall(cmd) => {
+                self.write_selection_to_temp_file_and_call(cmd, false)
+            }
+            ExternalMsg::WriteNulSeparatedSelectionToTempFileAndCall(cmd) => {
+                self.write_selection_to_temp_file_and_call(cmd, true)
+            }
             ExternalMsg::Select => self.select(),
             ExternalMsg::UnSelect => self.un_select(),
             ExternalMsg::ToggleSelection => self.toggle_selection(),
+            ExternalMsg::SelectAndFocusNext => self.and_focus_next(Self::select),
+            ExternalMsg::UnSelectAndFocusNext => self.and_focus_next(Self::un_select),
+            ExternalMsg::ToggleSelectionAndFocusNext => self.and_focus_next(Self::toggle_selection),
+            ExternalMsg::SelectRangeFromLastFocus => self.select_range_from_last_focus(),
+            ExternalMsg::SetSelectionAnchor => self.set_selection_anchor(),
+            ExternalMsg::ClearSelectionAnchor => self.clear_selection_anchor(),
+            ExternalMsg::SelectToAnchor => self.select_to_anchor(),
+            ExternalMsg::ToggleSelectionByMime(m) => self.toggle_selection_by_mime(&m),
+            ExternalMsg::SetSelection(paths) => self.set_selection(paths),
             ExternalMsg::ClearSelection => self.clear_selection(),
+            ExternalMsg::RenameSelectionWithTemplate(t) => {
+                if self.config.general.confirm_destructive {
+                    self.ask_confirmation(ExternalMsg::RenameSelectionWithTemplate(t))
+                } else {
+                    self.rename_selection_with_template(&t)
+                }
+            }
+            ExternalMsg::ConfirmPending => self.confirm_pending(),
+            ExternalMsg::CancelPending => self.cancel_pending(),
+            ExternalMsg::ClearSelectionInCurrentDirectory => {
+                self.clear_selection_in_current_directory()
+            }
             ExternalMsg::AddNodeFilter(f) => self.add_node_filter(f),
             ExternalMsg::AddNodeFilterFromInput(f) => self.add_node_filter_from_input(f),
             ExternalMsg::RemoveNodeFilter(f) => self.remove_node_filter(f),
             ExternalMsg::ToggleNodeFilter(f) => self.toggle_node_filter(f),
+            ExternalMsg::ToggleHidden => self.toggle_hidden(),
+            ExternalMsg::ToggleFilterByFocusedExtension => {
+                self.toggle_filter_by_focused_extension()
+            }
+            ExternalMsg::PreviewFilterFromInput(f) => self.preview_filter_from_input(f),
             ExternalMsg::ResetNodeFilters => self.reset_node_filters(),
+            ExternalMsg::ShowOnlyMimeCategory(c) => self.show_only_mime_category(&c),
+            ExternalMsg::ShowAllMimeCategories => self.show_all_mime_categories(),
+            ExternalMsg::SortNodes(s) => self.sort_nodes(s),
+            ExternalMsg::SwapFocusWithNext => self.swap_focus_with_next(),
+            ExternalMsg::SwapFocusWithPrevious => self.swap_focus_with_previous(),
             ExternalMsg::LogInfo(l) => self.log_info(l),
+            ExternalMsg::LogWarning(l) => self.log_warning(l),
             ExternalMsg::LogSuccess(l) => self.log_success(l),
             ExternalMsg::LogError(l) => self.log_error(l),
             ExternalMsg::PrintResultAndQuit => self.print_result_and_quit(),
+            ExternalMsg::PrintRelativePathAndQuit(base) => self.print_relative_path_and_quit(base),
             ExternalMsg::PrintAppStateAndQuit => self.print_app_state_and_quit(),
+            ExternalMsg::PrintFocusedMetadataAndQuit => self.print_focused_metadata_and_quit(),
+            ExternalMsg::PrintPwdAndQuit => self.print_pwd_and_quit(),
+            ExternalMsg::PrintSelectionStatsAndQuit => self.print_selection_stats_and_quit(),
+            ExternalMsg::PrintFocusedAndSelectionAndQuit => {
+                self.print_focused_and_selection_and_quit()
+            }
             ExternalMsg::Debug(path) => self.debug(&path),
-            ExternalMsg::Terminate => bail!("terminated"),
+            ExternalMsg::SelfCheckAndLog => self.self_check_and_log(),
+            ExternalMsg::ExplainKey(key) => self.explain_key(&key),
+            ExternalMsg::Sleep(millis) => self.sleep(millis),
+            ExternalMsg::Reset => self.reset(),
+            ExternalMsg::Terminate => Err(AppError::Terminated.into()),
         }
     }
 
-    fn handle_key(mut self, key: Key) -> Result<Self> {
-        let kb = self.mode.key_bindings.clone();
-        let default = kb.default.clone();
-        let msgs = kb
-            .on_key
-            .get(&key.to_string())
-            .map(|a| Some(a.messages.clone()))
-            .unwrap_or_else(|| {
+    /// Find the binding that handles the key in the current mode, with its
+    /// name, e.g. `on_key.enter` or `on_alphabet`.
+    fn resolve_key(&self, key: &Key) -> Option<(String, Action)> {
+        let kb = &self.mode.key_bindings;
+        let key_str = key.to_string();
+        kb.on_key
+            .get(&key_str)
+            .map(|a| (format!("on_key.{}", key_str), a.clone()))
+            .or_else(|| {
                 if key.is_alphabet() {
-                    kb.on_alphabet.map(|a| a.messages)
+                    kb.on_alphabet.clone().map(|a| ("on_alphabet".into(), a))
                 } else if key.is_number() {
-                    kb.on_number.map(|a| a.messages)
+                    kb.on_number.clone().map(|a| ("on_number".into(), a))
                 } else if key.is_special_character() {
-                    kb.on_special_character.map(|a| a.messages)
+                    kb.on_special_character
+                        .clone()
+                        .map(|a| ("on_special_character".into(), a))
                 } else {
                     None
                 }
             })
-            .unwrap_or_else(|| default.map(|a| a.messages).unwrap_or_default());
+            .or_else(|| kb.default.clone().map(|a| ("default".into(), a)))
+    }
+
+    fn handle_key(mut self, key: Key) -> Result<Self> {
+        let msgs = self
+            .resolve_key(&key)
+            .map(|(_, a)| a.messages)
+            .unwrap_or_default();
 
         for msg in msgs {
             self = self.enqueue(Task::new(0, MsgIn::External(msg), Some(key)));
@@ -874,21 +2356,194 @@ impl App {
         Ok(self)
     }
 
+    fn explain_key(self, key: &String) -> Result<Self> {
+        let parsed = serde_yaml::from_str::<Key>(key).unwrap_or_else(|_| key.as_str().into());
+        let message = match self.resolve_key(&parsed) {
+            Some((name, action)) => format!(
+                "key {} in mode {} is handled by {}: {:?}",
+                key, &self.mode.name, name, action.messages
+            ),
+            None => format!("key {} in mode {} is not handled", key, &self.mode.name),
+        };
+        self.log_info(message)
+    }
+
+    /// Advance the registered animations. Nothing to do (not even a refresh)
+    /// if there's none.
+    fn tick(mut self) -> Result<Self> {
+        if !self.animations.is_empty() {
+            for frame in self.animations.values_mut() {
+                *frame = frame.wrapping_add(1);
+            }
+            self.msg_out.push_back(MsgOut::Refresh);
+        };
+        Ok(self)
+    }
+
+    fn set_layout(mut self, layout: ScreenLayout) -> Result<Self> {
+        self.layout = layout;
+        Ok(self)
+    }
+
     fn explore(mut self) -> Result<Self> {
+        self.exploring_recursively = false;
+        self.explored_directory = Some(self.pwd.clone());
         self.msg_out.push_back(MsgOut::Explore);
         Ok(self)
     }
 
+    fn explore_recursive(mut self, max_depth: Option<usize>) -> Result<Self> {
+        if max_depth == Some(0) {
+            return self.log_error("max_depth must be at least 1".into());
+        }
+        self.exploring_recursively = true;
+        self.msg_out.push_back(MsgOut::ExploreRecursive(max_depth));
+        Ok(self)
+    }
+
+    fn begin_batch(mut self) -> Result<Self> {
+        self.suppress_refresh = true;
+        Ok(self)
+    }
+
+    fn end_batch(mut self) -> Result<Self> {
+        self.suppress_refresh = false;
+        self.msg_out.push_back(MsgOut::Refresh);
+        Ok(self)
+    }
+
     fn refresh(mut self) -> Result<Self> {
         self.msg_out.push_back(MsgOut::Refresh);
         Ok(self)
     }
 
+    fn hard_refresh(mut self) -> Result<Self> {
+        let buffers = self.directory_buffers.len();
+        let selection = self.selection.len();
+
+        self.directory_buffers.clear();
+        self.checksums.clear();
+        self = self.refresh_selection()?;
+
+        let message = format!(
+            "cleared {} directory buffers and {} vanished selections",
+            buffers,
+            selection - self.selection.len()
+        );
+        self.log_info(message)?.explore()
+    }
+
     fn clear_screen(mut self) -> Result<Self> {
         self.msg_out.push_back(MsgOut::ClearScreen);
         Ok(self)
     }
 
+    fn clear_screen_and_refresh(self) -> Result<Self> {
+        self.clear_screen()?.refresh()
+    }
+
+    fn focus_random(mut self) -> Result<Self> {
+        // xorshift64, good enough for sampling, and deterministic for a given seed.
+        let mut seed = self.random_seed.max(1);
+        seed ^= seed << 13;
+        seed ^= seed >> 7;
+        seed ^= seed << 17;
+        self.random_seed = seed;
+
+        if let Some(dir) = self.directory_buffer_mut() {
+            if !dir.nodes.is_empty() {
+                dir.focus = (seed % dir.nodes.len() as u64) as usize;
+                self.msg_out.push_back(MsgOut::Refresh);
+            };
+        };
+        Ok(self)
+    }
+
+    /// Focus on the next (or previous) node matching the predicate, wrapping
+    /// around once.
+    fn focus_next_matching<F>(mut self, forward: bool, kind: &str, predicate: F) -> Result<Self>
+    where
+        F: Fn(&Node) -> bool,
+    {
+        let mut found = false;
+        if let Some(dir) = self.directory_buffer_mut() {
+            let total = dir.nodes.len();
+            for step in 1..=total {
+                let index = if forward {
+                    (dir.focus + step) % total
+                } else {
+                    (dir.focus + total - step) % total
+                };
+
+                if predicate(&dir.nodes[index]) {
+                    dir.focus = index;
+                    found = true;
+                    break;
+                };
+            }
+        };
+
+        if found {
+            self.msg_out.push_back(MsgOut::Refresh);
+            Ok(self)
+        } else {
+            self.log_info(format!("no {} found", kind))
+        }
+    }
+
+    fn compute_focused_checksum(mut self) -> Result<Self> {
+        match self.focused_node().filter(|n| n.is_file).cloned() {
+            Some(node) => match Checksum::of(&node.absolute_path) {
+                Ok(checksum) => {
+                    let message = format!("{}  {}", &checksum.digest, &node.relative_path);
+                    self.checksums.insert(node.absolute_path, checksum);
+                    self.log_info(message)
+                }
+                Err(e) => self.log_error(format!(
+                    "failed to compute the checksum of {}: {}",
+                    &node.absolute_path, e
+                )),
+            },
+            None => self.log_info("the focused node is not a file".into()),
+        }
+    }
+
+    fn focus_next_duplicate(self) -> Result<Self> {
+        // The files may have changed since, so the outdated checksums don't
+        // count.
+        let digest = |checksums: &HashMap<String, Checksum>, path: &String| {
+            checksums
+                .get(path)
+                .filter(|c| c.is_fresh(path))
+                .map(|c| c.digest.clone())
+        };
+
+        let focused = self.focused_node().map(|n| n.absolute_path.clone());
+        match focused.and_then(|p| digest(&self.checksums, &p).map(|d| (p, d))) {
+            Some((path, checksum)) => {
+                let checksums = self.checksums.clone();
+                self.focus_next_matching(true, "duplicate", |n| {
+                    n.absolute_path != path
+                        && digest(&checksums, &n.absolute_path) == Some(checksum.clone())
+                })
+            }
+            None => self.log_info(
+                "the checksum of the focused node isn't computed yet, or is outdated".into(),
+            ),
+        }
+    }
+
+    fn focus_next_unselected(self, forward: bool) -> Result<Self> {
+        let selected = self
+            .selection
+            .iter()
+            .map(|n| n.absolute_path.clone())
+            .collect::<HashSet<String>>();
+        self.focus_next_matching(forward, "unselected node", |n| {
+            !selected.contains(&n.absolute_path)
+        })
+    }
+
     fn focus_first(mut self) -> Result<Self> {
         if let Some(dir) = self.directory_buffer_mut() {
             dir.focus = 0;
@@ -899,7 +2554,7 @@ impl App {
 
     fn focus_last(mut self) -> Result<Self> {
         if let Some(dir) = self.directory_buffer_mut() {
-            dir.focus = dir.total.max(1) - 1;
+            dir.focus = dir.nodes.len().max(1) - 1;
             self.msg_out.push_back(MsgOut::Refresh);
         };
         Ok(self)
@@ -931,7 +2586,7 @@ impl App {
 
     fn focus_next(mut self) -> Result<Self> {
         if let Some(dir) = self.directory_buffer_mut() {
-            dir.focus = (dir.focus + 1).min(dir.total.max(1) - 1);
+            dir.focus = (dir.focus + 1).min(dir.nodes.len().max(1) - 1);
             self.msg_out.push_back(MsgOut::Refresh);
         };
         Ok(self)
@@ -939,7 +2594,7 @@ impl App {
 
     fn focus_next_by_relative_index(mut self, index: usize) -> Result<Self> {
         if let Some(dir) = self.directory_buffer_mut() {
-            dir.focus = (dir.focus + index).min(dir.total.max(1) - 1);
+            dir.focus = (dir.focus + index).min(dir.nodes.len().max(1) - 1);
             self.msg_out.push_back(MsgOut::Refresh);
         };
         Ok(self)
@@ -953,29 +2608,104 @@ impl App {
         }
     }
 
+    fn focus_next_by_page(self) -> Result<Self> {
+        let page = self.layout.list_height.max(1) as usize;
+        self.focus_next_by_relative_index(page)
+    }
+
+    fn focus_previous_by_page(self) -> Result<Self> {
+        let page = self.layout.list_height.max(1) as usize;
+        self.focus_previous_by_relative_index(page)
+    }
+
     fn change_directory(mut self, dir: &String) -> Result<Self> {
+        // The relative paths are relative to the present working directory, not
+        // to where xplr was started.
+        let dir = &if Path::new(dir).is_relative() {
+            match PathBuf::from(&self.pwd).join(dir).canonicalize() {
+                Ok(path) => path.to_string_lossy().to_string(),
+                Err(_) => return Ok(self),
+            }
+        } else {
+            dir.to_owned()
+        };
+
         if PathBuf::from(dir).is_dir() {
+            if &self.pwd != dir {
+                self.exploring_recursively = false;
+                self.last_selected_index = None;
+                self.selection_anchor = None;
+                self.pending_focus = self.pending_focus.take().filter(|(d, _)| d == dir);
+                self.explored_directory = Some(dir.to_owned());
+                self.last_pwd = Some(self.pwd.clone());
+            };
             self.pwd = dir.to_owned();
-            self.msg_out.push_back(MsgOut::Refresh);
+            if self.config.general.explore_on_change_directory {
+                self.msg_out.push_back(MsgOut::Explore);
+            } else {
+                self.msg_out.push_back(MsgOut::Refresh);
+            };
         };
         Ok(self)
     }
 
-    fn enter(self) -> Result<Self> {
-        self.focused_node()
-            .map(|n| n.absolute_path.clone())
-            .map(|p| self.clone().change_directory(&p))
-            .unwrap_or(Ok(self))
+    fn toggle_last_directory(self) -> Result<Self> {
+        if let Some(dir) = self.last_pwd.clone() {
+            self.change_directory(&dir)
+        } else {
+            Ok(self)
+        }
+    }
+
+    fn enter(mut self) -> Result<Self> {
+        if let Some(node) = self.focused_node().cloned() {
+            if node.is_file && self.config.general.enter_file_focuses_parent {
+                // In a recursive listing, the relative path can have more
+                // than one component.
+                let path = PathBuf::from(&node.absolute_path);
+                match (path.parent(), path.file_name()) {
+                    (Some(parent), Some(name)) => {
+                        let parent = parent.to_string_lossy().to_string();
+                        let name = name.to_string_lossy().to_string();
+                        if self.directory_buffers.contains_key(&parent) {
+                            self.change_directory(&parent)?.focus_by_file_name(&name)
+                        } else {
+                            self.explore_and_focus(&node.absolute_path)
+                        }
+                    }
+                    _ => Ok(self),
+                }
+            } else {
+                let path = if node.is_symlink && self.config.general.explore_symlinks_as_dirs {
+                    // Stay in the namespace of the link, whatever the symlink
+                    // resolution is.
+                    node.lexical_path().to_string_lossy().to_string()
+                } else {
+                    node.absolute_path.clone()
+                };
+                if node.is_dir && self.config.general.follow_focus_into_empty {
+                    // Checked once the directory is explored.
+                    self.entered_directory = Some(path.clone());
+                };
+                self.change_directory(&path)
+            }
+        } else {
+            Ok(self)
+        }
     }
 
     fn back(self) -> Result<Self> {
-        PathBuf::from(self.pwd())
-            .parent()
-            .map(|p| {
-                self.clone()
-                    .change_directory(&p.to_string_lossy().to_string())
-            })
-            .unwrap_or(Ok(self))
+        if self.exploring_recursively {
+            return self.explore();
+        };
+
+        match PathBuf::from(self.pwd()).parent() {
+            Some(p) => {
+                let parent = p.to_string_lossy().to_string();
+                self.change_directory(&parent)
+            }
+            None => self.log_info("already at root".into()),
+        }
     }
 
     fn buffer_input(mut self, input: &String) -> Result<Self> {
@@ -1008,9 +2738,62 @@ impl App {
         Ok(self)
     }
 
+    fn clear_input_buffer(mut self) -> Result<Self> {
+        self.input_buffer = Some(String::new());
+        self.msg_out.push_back(MsgOut::Refresh);
+        Ok(self)
+    }
+
+    fn remember_input(&mut self, input: &str) {
+        self.input_history_index = None;
+        if input.is_empty()
+            || self
+                .input_history
+                .back()
+                .map(|i| i == input)
+                .unwrap_or(false)
+        {
+            return;
+        };
+        self.input_history.push_back(input.to_string());
+        while self.input_history.len() > self.config.general.input_history_size {
+            self.input_history.pop_front();
+        }
+    }
+
+    fn recall_previous_input(mut self) -> Result<Self> {
+        if self.input_history.is_empty() {
+            return Ok(self);
+        };
+        let index = self
+            .input_history_index
+            .map(|i| i.saturating_sub(1))
+            .unwrap_or(self.input_history.len() - 1);
+        self.input_history_index = Some(index);
+        self.input_buffer = self.input_history.get(index).cloned();
+        self.msg_out.push_back(MsgOut::Refresh);
+        Ok(self)
+    }
+
+    fn recall_next_input(mut self) -> Result<Self> {
+        match self.input_history_index {
+            Some(i) if i + 1 < self.input_history.len() => {
+                self.input_history_index = Some(i + 1);
+                self.input_buffer = self.input_history.get(i + 1).cloned();
+            }
+            Some(_) => {
+                self.input_history_index = None;
+                self.input_buffer = Some(String::new());
+            }
+            None => return Ok(self),
+        };
+        self.msg_out.push_back(MsgOut::Refresh);
+        Ok(self)
+    }
+
     fn focus_by_index(mut self, index: usize) -> Result<Self> {
         if let Some(dir) = self.directory_buffer_mut() {
-            dir.focus = index.min(dir.total.max(1) - 1);
+            dir.focus = index.min(dir.nodes.len().max(1) - 1);
             self.msg_out.push_back(MsgOut::Refresh);
         };
         Ok(self)
@@ -1024,6 +2807,28 @@ impl App {
         }
     }
 
+    fn focus_by_index_or_relative_from_input(self) -> Result<Self> {
+        if let Some(input) = self.input_buffer() {
+            let input = input.trim();
+            let (sign, num) = if let Some(num) = input.strip_prefix('+') {
+                (Some('+'), num)
+            } else if let Some(num) = input.strip_prefix('-') {
+                (Some('-'), num)
+            } else {
+                (None, input)
+            };
+
+            match (sign, num.parse::<usize>()) {
+                (Some('+'), Ok(index)) => self.focus_next_by_relative_index(index),
+                (Some(_), Ok(index)) => self.focus_previous_by_relative_index(index),
+                (None, Ok(index)) => self.focus_by_index(index),
+                (_, Err(e)) => self.log_error(format!("invalid index {}: {}", input, e)),
+            }
+        } else {
+            Ok(self)
+        }
+    }
+
     fn focus_by_file_name(mut self, name: &String) -> Result<Self> {
         if let Some(dir_buf) = self.directory_buffer_mut() {
             if let Some(focus) = dir_buf
@@ -1041,12 +2846,48 @@ impl App {
         Ok(self)
     }
 
+    fn focus_by_stem(mut self, stem: &String) -> Result<Self> {
+        if let Some(dir_buf) = self.directory_buffer_mut() {
+            let stems = dir_buf
+                .nodes
+                .iter()
+                .map(|n| {
+                    PathBuf::from(&n.relative_path)
+                        .file_stem()
+                        .map(|s| s.to_string_lossy().to_string())
+                        .unwrap_or_default()
+                })
+                .collect::<Vec<String>>();
+
+            let maybe_focus = stems.iter().position(|s| s == stem).or_else(|| {
+                stems
+                    .iter()
+                    .position(|s| s.to_lowercase() == stem.to_lowercase())
+            });
+
+            if let Some(focus) = maybe_focus {
+                dir_buf.focus = focus;
+                self.msg_out.push_back(MsgOut::Refresh);
+            } else {
+                self = self.log_info(format!("no file with stem {}", stem))?;
+            };
+        };
+        Ok(self)
+    }
+
     fn focus_path(self, path: &String) -> Result<Self> {
         let pathbuf = PathBuf::from(path);
         if let Some(parent) = pathbuf.parent() {
             if let Some(filename) = pathbuf.file_name() {
-                self.change_directory(&parent.to_string_lossy().to_string())?
-                    .focus_by_file_name(&filename.to_string_lossy().to_string())
+                let parent = parent.to_string_lossy().to_string();
+                let filename = filename.to_string_lossy().to_string();
+                if parent == self.pwd {
+                    // Keep the buffer, there's nothing to explore.
+                    self.focus_by_file_name(&filename)
+                } else {
+                    self.change_directory(&parent)?
+                        .focus_by_file_name(&filename)
+                }
             } else {
                 Ok(self)
             }
@@ -1055,16 +2896,148 @@ impl App {
         }
     }
 
-    fn focus_path_from_input(self) -> Result<Self> {
+    fn focus_path_from_input(mut self) -> Result<Self> {
         if let Some(p) = self.input_buffer() {
+            self.remember_input(&p);
             self.focus_path(&p)
         } else {
             Ok(self)
         }
     }
 
+    fn focus_by_glob(mut self, glob: &str) -> Result<Self> {
+        if let Err(e) = validate_glob(glob) {
+            return self.log_error(e);
+        };
+
+        let focus = self.directory_buffer().and_then(|d| {
+            d.nodes
+                .iter()
+                .position(|n| glob_matches(glob, &n.relative_path))
+        });
+
+        match (focus, self.directory_buffer_mut()) {
+            (Some(focus), Some(dir)) => {
+                dir.focus = focus;
+                self.msg_out.push_back(MsgOut::Refresh);
+                Ok(self)
+            }
+            _ => self.log_info(format!("no node matches {}", glob)),
+        }
+    }
+
+    fn focus_by_glob_from_input(mut self) -> Result<Self> {
+        if let Some(glob) = self.input_buffer() {
+            self.remember_input(&glob);
+            self.focus_by_glob(&glob)
+        } else {
+            Ok(self)
+        }
+    }
+
+    fn explore_and_focus(mut self, path: &String) -> Result<Self> {
+        let pathbuf = PathBuf::from(path);
+        if let (Some(parent), Some(name)) = (pathbuf.parent(), pathbuf.file_name()) {
+            let parent = parent.to_string_lossy().to_string();
+            self.pending_focus = Some((parent.clone(), name.to_string_lossy().to_string()));
+            self.change_directory(&parent)?.explore()
+        } else {
+            Ok(self)
+        }
+    }
+
+    fn focus_by_absolute_path(self, path: &String) -> Result<Self> {
+        match PathBuf::from(path).canonicalize() {
+            Ok(pathbuf) => {
+                let path = pathbuf.to_string_lossy().to_string();
+                let buffered = pathbuf
+                    .parent()
+                    .map(|p| {
+                        self.directory_buffers
+                            .contains_key(&p.to_string_lossy().to_string())
+                    })
+                    .unwrap_or(false);
+
+                if buffered {
+                    self.focus_path(&path)
+                } else {
+                    self.explore_and_focus(&path)
+                }
+            }
+            Err(e) => self.log_error(format!("can't focus on {}: {}", path, e)),
+        }
+    }
+
+    fn focus_path_or_create(mut self, path: &String) -> Result<Self> {
+        let target = normalize_path(&PathBuf::from(&self.pwd).join(path));
+        if target == Path::new(&self.pwd) || !target.starts_with(&self.pwd) {
+            let message = format!("refusing to create {} outside of {}", path, &self.pwd);
+            return self.log_error(message);
+        };
+
+        let is_dir = path.ends_with('/');
+        let dir = if is_dir {
+            Some(target.as_path())
+        } else {
+            target.parent()
+        };
+
+        if let Some(dir) = dir.filter(|d| !d.exists()) {
+            match fs::create_dir_all(dir) {
+                Ok(()) => {
+                    self =
+                        self.log_success(format!("created directory {}", dir.to_string_lossy()))?
+                }
+                Err(e) => {
+                    return self.log_error(format!(
+                        "failed to create directory {}: {}",
+                        dir.to_string_lossy(),
+                        e
+                    ))
+                }
+            };
+        };
+
+        if !is_dir && !target.exists() {
+            match fs::File::create(&target) {
+                Ok(_) => {
+                    self = self.log_success(format!("created file {}", target.to_string_lossy()))?
+                }
+                Err(e) => {
+                    return self.log_error(format!(
+                        "failed to create file {}: {}",
+                        target.to_string_lossy(),
+                        e
+                    ))
+                }
+            };
+        };
+
+        // The parent needs to be explored again to find the new nodes.
+        self.explore_and_focus(&target.to_string_lossy().to_string())
+    }
+
+    fn focus_first_selection_across_dirs(mut self) -> Result<Self> {
+        for node in self.selection.clone() {
+            if node.parent == self.pwd {
+                continue;
+            } else if !PathBuf::from(&node.absolute_path).exists() {
+                self = self.log_error(format!(
+                    "skipping {}, it doesn't exist",
+                    &node.absolute_path
+                ))?;
+            } else {
+                return self.focus_by_absolute_path(&node.absolute_path);
+            };
+        }
+        self.log_info("no selected node outside the present working directory".into())
+    }
+
     fn switch_mode(mut self, mode: &String) -> Result<Self> {
         if let Some(mode) = self.config.modes.get(mode) {
+            if mode.clear_logs_on_enter {
+                self.logs.clear();
+            };
             self.input_buffer = None;
             self.mode = mode.to_owned();
             self.msg_out.push_back(MsgOut::Refresh);
@@ -1072,19 +3045,124 @@ impl App {
         Ok(self)
     }
 
+    fn switch_mode_keeping_input(mut self, mode: &String) -> Result<Self> {
+        if let Some(mode) = self.config.modes.get(mode) {
+            if mode.clear_logs_on_enter {
+                self.logs.clear();
+            };
+            self.mode = mode.to_owned();
+            self.msg_out.push_back(MsgOut::Refresh);
+        };
+        Ok(self)
+    }
+
     fn call(mut self, command: Command) -> Result<Self> {
         self.msg_out.push_back(MsgOut::Call(command));
         Ok(self)
     }
 
-    fn add_directory(mut self, parent: String, dir: DirectoryBuffer) -> Result<Self> {
+    fn write_selection_to_temp_file_and_call(
+        mut self,
+        command: Command,
+        nul_separated: bool,
+    ) -> Result<Self> {
+        self.msg_out.push_back(MsgOut::CallWithSelectionFile {
+            command,
+            nul_separated,
+        });
+        Ok(self)
+    }
+
+    fn add_directory(mut self, parent: String, mut dir: DirectoryBuffer) -> Result<Self> {
+        // A fresh buffer has no idea where the cursor was last time, so
+        // restore it from the focus we remember for this directory, unless the
+        // explorer already knows which node to focus on. The index may point
+        // to another node by now.
+        if !self.directory_buffers.contains_key(&parent) && !dir.focused_by_name {
+            if let Some(focus) = self.last_focus.get(&parent) {
+                dir.focus = (*focus).min(dir.nodes.len().max(1) - 1);
+            };
+        };
+
+        // Several buffers of the same directory can be on their way, so the
+        // pending focus is kept until one of them has the node.
+        if let Some((pending_dir, name)) = self.pending_focus.clone() {
+            if pending_dir == parent {
+                if let Some(focus) = dir.nodes.iter().position(|n| n.relative_path == name) {
+                    dir.focus = focus;
+                    self.pending_focus = None;
+                };
+            };
+        };
+
+        let entered_empty = self.entered_directory.as_ref() == Some(&parent);
+        if entered_empty {
+            self.entered_directory = None;
+        };
+        let entered_empty = entered_empty && dir.nodes.is_empty();
+
+        // Only the first buffer after exploring, so that the refreshes don't
+        // fight the user. A truncated buffer may be hiding the other matches.
+        let explored = self.explored_directory.as_ref() == Some(&parent);
+        if explored {
+            self.explored_directory = None;
+        };
+        let single = if explored
+            && self.config.general.auto_select_on_enter_single
+            && parent == self.pwd
+            && !dir.is_truncated()
+            && dir.nodes.len() == 1
+            && dir.nodes[0].is_file
+        {
+            dir.focus = 0;
+            Some(dir.nodes[0].relative_path.clone())
+        } else {
+            None
+        };
+
+        self.last_focus.insert(parent.clone(), dir.focus);
         self.directory_buffers.insert(parent, dir);
         self.msg_out.push_back(MsgOut::Refresh);
-        Ok(self)
+
+        if entered_empty {
+            self = self.log_info("empty directory".into())?;
+        };
+
+        if let Some(name) = single {
+            for msg in self
+                .config
+                .general
+                .auto_select_on_enter_single_messages
+                .clone()
+            {
+                self = self.enqueue(Task::new(0, MsgIn::External(msg), None));
+            }
+            self.log_info(format!("focused the only node {}", name))
+        } else {
+            Ok(self)
+        }
+    }
+
+    fn is_selection_full(&self) -> bool {
+        self.config
+            .general
+            .max_selection
+            .map(|max| self.selection.len() >= max)
+            .unwrap_or(false)
+    }
+
+    fn log_selection_full(self) -> Result<Self> {
+        let max = self.config.general.max_selection.unwrap_or_default();
+        self.log_error(format!("can't select more than {} nodes", max))
     }
 
     fn select(mut self) -> Result<Self> {
+        if self.focused_node().is_some() && self.is_selection_full() {
+            return self.log_selection_full();
+        };
+
         if let Some(n) = self.focused_node().map(|n| n.to_owned()) {
+            self.last_selected_index = self.directory_buffer().map(|d| d.focus);
             self.selection.push(n.clone());
             self.msg_out.push_back(MsgOut::Refresh);
         }
@@ -1115,12 +3193,227 @@ impl App {
         Ok(self)
     }
 
+    fn select_range_from_last_focus(self) -> Result<Self> {
+        let anchor = self.last_selected_index;
+        self.select_range_from(anchor)
+    }
+
+    fn set_selection_anchor(mut self) -> Result<Self> {
+        if let Some(focus) = self.focused_index() {
+            self.selection_anchor = Some(focus);
+            self.msg_out.push_back(MsgOut::Refresh);
+        };
+        Ok(self)
+    }
+
+    fn clear_selection_anchor(mut self) -> Result<Self> {
+        self.selection_anchor = None;
+        self.msg_out.push_back(MsgOut::Refresh);
+        Ok(self)
+    }
+
+    fn select_to_anchor(self) -> Result<Self> {
+        match self.selection_anchor {
+            Some(anchor) => self.select_range_from(Some(anchor)),
+            None => self.log_info("no selection anchor set".into()),
+        }
+    }
+
+    /// Select every node between the anchor, or else the focused node, and the
+    /// focused node.
+    fn select_range_from(mut self, anchor: Option<usize>) -> Result<Self> {
+        if let Some(dir) = self.directory_buffer().cloned() {
+            if dir.nodes.is_empty() {
+                return Ok(self);
+            };
+
+            let last = dir.nodes.len() - 1;
+            let focus = dir.focus.min(last);
+            let anchor = anchor.unwrap_or(focus).min(last);
+
+            for node in &dir.nodes[anchor.min(focus)..=anchor.max(focus)] {
+                if self.selection.contains(node) {
+                    continue;
+                } else if self.is_selection_full() {
+                    self = self.log_selection_full()?;
+                    break;
+                } else {
+                    self.selection.push(node.clone());
+                };
+            }
+
+            self.last_selected_index = Some(focus);
+            self.msg_out.push_back(MsgOut::Refresh);
+        };
+        Ok(self)
+    }
+
+    fn toggle_selection_by_mime(mut self, mime: &String) -> Result<Self> {
+        let nodes = self
+            .directory_buffer()
+            .map(|d| {
+                d.nodes
+                    .iter()
+                    .filter(|n| n.mime_essence.starts_with(mime))
+                    .cloned()
+                    .collect::<Vec<Node>>()
+            })
+            .unwrap_or_default();
+
+        let (mut selected, mut unselected) = (0, 0);
+        for node in nodes {
+            if self.selection.contains(&node) {
+                self.selection.retain(|n| n != &node);
+                unselected += 1;
+            } else if self.is_selection_full() {
+                self = self.log_selection_full()?;
+                break;
+            } else {
+                self.selection.push(node);
+                selected += 1;
+            };
+        }
+
+        self.msg_out.push_back(MsgOut::Refresh);
+        self.log_info(format!(
+            "selected {} and unselected {} nodes",
+            selected, unselected
+        ))
+    }
+
+    fn set_selection(mut self, paths: Vec<String>) -> Result<Self> {
+        self.selection.clear();
+        self.msg_out.push_back(MsgOut::Refresh);
+
+        for path in paths {
+            let pathbuf = PathBuf::from(&path);
+            let parent = pathbuf.parent().map(|p| p.to_string_lossy().to_string());
+            let name = pathbuf.file_name().map(|n| n.to_string_lossy().to_string());
+
+            match (parent, name) {
+                (Some(parent), Some(name)) if pathbuf.exists() => {
+                    if self.is_selection_full() {
+                        return self.log_selection_full();
+                    };
+                    let node = Node::with_symlink_resolution(
+                        parent,
+                        name,
+                        self.explorer_config.symlink_resolution,
+                    );
+                    if !self.selection.contains(&node) {
+                        self.selection.push(node);
+                    };
+                }
+                _ => {
+                    self = self.log_warning(format!("skipped selecting {}: not found", path))?;
+                }
+            };
+        }
+
+        Ok(self)
+    }
+
     fn clear_selection(mut self) -> Result<Self> {
         self.selection.clear();
         self.msg_out.push_back(MsgOut::Refresh);
         Ok(self)
     }
 
+    fn ask_confirmation(mut self, msg: ExternalMsg) -> Result<Self> {
+        let message = format!(
+            "{:?} needs confirmation, send ConfirmPending to proceed or CancelPending to discard",
+            &msg
+        );
+        self.pending_confirmation = Some(msg);
+        self.msg_out.push_back(MsgOut::Refresh);
+        self.log_warning(message)
+    }
+
+    fn confirm_pending(mut self) -> Result<Self> {
+        match self.pending_confirmation.take() {
+            Some(ExternalMsg::RenameSelectionWithTemplate(t)) => {
+                self.rename_selection_with_template(&t)
+            }
+            Some(_) | None => self.log_info("nothing to confirm".into()),
+        }
+    }
+
+    fn cancel_pending(mut self) -> Result<Self> {
+        if let Some(msg) = self.pending_confirmation.take() {
+            self.log_info(format!("cancelled {:?}", msg))
+        } else {
+            self.log_info("nothing to cancel".into())
+        }
+    }
+
+    fn rename_selection_with_template(mut self, template: &str) -> Result<Self> {
+        let mut renames = vec![];
+        for (i, node) in self.selection.iter().enumerate() {
+            let source = node.lexical_path();
+            let name = node.file_name();
+
+            match render_rename_template(template, i + 1, &name) {
+                Ok(new_name) => {
+                    let target = source.with_file_name(new_name);
+                    renames.push((source, target));
+                }
+                Err(e) => return self.log_error(format!("can't rename {}: {}", name, e)),
+            };
+        }
+
+        // Check everything first, so that a clash doesn't leave it half done.
+        let mut targets = HashSet::new();
+        for (source, target) in renames.iter() {
+            if !targets.insert(target.clone()) {
+                let message = format!(
+                    "can't rename: more than one node would be {}",
+                    target.to_string_lossy()
+                );
+                return self.log_error(message);
+            };
+            if target != source && target.symlink_metadata().is_ok() {
+                let message = format!(
+                    "can't rename {}: {} already exists",
+                    source.to_string_lossy(),
+                    target.to_string_lossy()
+                );
+                return self.log_error(message);
+            };
+        }
+
+        let mut renamed = 0;
+        for (source, target) in renames {
+            if let Err(e) = fs::rename(&source, &target) {
+                self = self.log_error(format!(
+                    "failed to rename {}: {}",
+                    source.to_string_lossy(),
+                    e
+                ))?;
+                break;
+            };
+
+            // Keep the renamed nodes selected.
+            if let (Some(parent), Some(name)) = (target.parent(), target.file_name()) {
+                self.selection[renamed] = Node::with_symlink_resolution(
+                    parent.to_string_lossy().to_string(),
+                    name.to_string_lossy().to_string(),
+                    self.explorer_config.symlink_resolution,
+                );
+            };
+            renamed += 1;
+        }
+
+        self.log_success(format!("renamed {} nodes", renamed))?
+            .explore()
+    }
+
+    fn clear_selection_in_current_directory(mut self) -> Result<Self> {
+        let pwd = self.pwd.clone();
+        self.selection.retain(|n| n.parent != pwd);
+        self.msg_out.push_back(MsgOut::Refresh);
+        Ok(self)
+    }
+
     fn add_node_filter(mut self, filter: NodeFilterApplicable) -> Result<Self> {
         self.explorer_config.filters.push(filter);
         self.msg_out.push_back(MsgOut::Refresh);
@@ -1129,6 +3422,7 @@ impl App {
 
     fn add_node_filter_from_input(mut self, filter: NodeFilterFromInput) -> Result<Self> {
         if let Some(input) = self.input_buffer() {
+            self.remember_input(&input);
             self.explorer_config.filters.push(NodeFilterApplicable::new(
                 filter.filter,
                 input,
@@ -1158,17 +3452,112 @@ impl App {
         }
     }
 
-    fn reset_node_filters(mut self) -> Result<Self> {
-        self.explorer_config.filters.clear();
+    fn toggle_hidden(mut self) -> Result<Self> {
+        let mut hidden = vec![dotfile_filter()];
+        hidden.extend(hidden_pattern_filters(&self.config));
 
-        if !self.config.general.show_hidden {
-            self.explorer_config.filters.push(NodeFilterApplicable::new(
-                NodeFilter::RelativePathDoesNotStartWith,
-                ".".into(),
-                Default::default(),
-            ));
+        let filters = &mut self.explorer_config.filters;
+        if filters.iter().any(|f| hidden.contains(f)) {
+            filters.retain(|f| !hidden.contains(f));
+        } else {
+            filters.extend(hidden);
         };
         self.msg_out.push_back(MsgOut::Refresh);
+        Ok(self)
+    }
+
+    fn toggle_filter_by_focused_extension(self) -> Result<Self> {
+        match self.focused_node().map(|n| n.extension.clone()) {
+            Some(ext) if !ext.is_empty() => {
+                let filter = NodeFilterApplicable::new(
+                    NodeFilter::RelativePathDoesNotEndWith,
+                    format!(".{}", ext),
+                    Default::default(),
+                );
+                self.toggle_node_filter(filter)?.explore()
+            }
+            Some(_) => self.log_info("the focused node has no extension".into()),
+            None => Ok(self),
+        }
+    }
+
+    fn preview_filter_from_input(self, filter: NodeFilterFromInput) -> Result<Self> {
+        if let Some(input) = self.input_buffer() {
+            let filter = NodeFilterApplicable::new(filter.filter, input, filter.case_sensitive);
+            let (passing, total) = self.preview_filter_impact(&filter);
+            self.log_info(format!(
+                "filter would hide {} of {} nodes",
+                total - passing,
+                total
+            ))
+        } else {
+            Ok(self)
+        }
+    }
+
+    fn sort_nodes(mut self, sorter: NodeSorterApplicable) -> Result<Self> {
+        let focus_follows_sort = self.config.general.focus_follows_sort;
+        if let Some(dir) = self.directory_buffer_mut() {
+            let focused = dir.focused_node().map(|n| n.absolute_path.clone());
+
+            // Stable, so the equal nodes keep their order.
+            dir.nodes.sort_by(|a, b| sorter.compare(a, b));
+
+            if focus_follows_sort {
+                if let Some(path) = focused {
+                    dir.focus = dir
+                        .nodes
+                        .iter()
+                        .position(|n| n.absolute_path == path)
+                        .unwrap_or(dir.focus);
+                };
+            };
+            dir.focus = dir.focus.min(dir.nodes.len().max(1) - 1);
+            self.msg_out.push_back(MsgOut::Refresh);
+        };
+        Ok(self)
+    }
+
+    fn swap_focus_with_next(mut self) -> Result<Self> {
+        if let Some(dir) = self.directory_buffer_mut() {
+            if dir.focus + 1 < dir.nodes.len() {
+                dir.nodes.swap(dir.focus, dir.focus + 1);
+                dir.focus += 1;
+                self.msg_out.push_back(MsgOut::Refresh);
+            };
+        };
+        Ok(self)
+    }
+
+    fn swap_focus_with_previous(mut self) -> Result<Self> {
+        if let Some(dir) = self.directory_buffer_mut() {
+            if dir.focus > 0 && dir.focus < dir.nodes.len() {
+                dir.nodes.swap(dir.focus, dir.focus - 1);
+                dir.focus -= 1;
+                self.msg_out.push_back(MsgOut::Refresh);
+            };
+        };
+        Ok(self)
+    }
+
+    fn show_only_mime_category(mut self, category: &str) -> Result<Self> {
+        self.explorer_config.mime_category_filter = Some(NodeFilterApplicable::new(
+            NodeFilter::MimeEssenceDoesStartWith,
+            format!("{}/", category.trim_end_matches('/')),
+            Default::default(),
+        ));
+        self.explore()
+    }
+
+    fn show_all_mime_categories(mut self) -> Result<Self> {
+        self.explorer_config.mime_category_filter = None;
+        self.explore()
+    }
+
+    fn reset_node_filters(mut self) -> Result<Self> {
+        self.explorer_config.filters = default_filters(&self.config);
+        self.explorer_config.mime_category_filter = None;
+        self.msg_out.push_back(MsgOut::Refresh);
 
         Ok(self)
     }
@@ -1178,6 +3567,11 @@ impl App {
         Ok(self)
     }
 
+    fn log_warning(mut self, message: String) -> Result<Self> {
+        self.logs.push(Log::new(LogLevel::Warning, message));
+        Ok(self)
+    }
+
     fn log_success(mut self, message: String) -> Result<Self> {
         self.logs.push(Log::new(LogLevel::Success, message));
         Ok(self)
@@ -1193,6 +3587,71 @@ impl App {
         Ok(self)
     }
 
+    fn print_focused_metadata_and_quit(mut self) -> Result<Self> {
+        if self.focused_node().is_some() {
+            self.msg_out.push_back(MsgOut::PrintFocusedMetadataAndQuit);
+            Ok(self)
+        } else {
+            self.log_error("no node is focused".into())
+        }
+    }
+
+    fn print_pwd_and_quit(mut self) -> Result<Self> {
+        self.msg_out.push_back(MsgOut::PrintPwdAndQuit);
+        Ok(self)
+    }
+
+    fn print_selection_stats_and_quit(mut self) -> Result<Self> {
+        self.msg_out.push_back(MsgOut::PrintSelectionStatsAndQuit);
+        Ok(self)
+    }
+
+    fn print_focused_and_selection_and_quit(mut self) -> Result<Self> {
+        self.msg_out
+            .push_back(MsgOut::PrintFocusedAndSelectionAndQuit);
+        Ok(self)
+    }
+
+    fn reset(mut self) -> Result<Self> {
+        self.selection.clear();
+        self.selection_anchor = None;
+        self.last_selected_index = None;
+        self.explorer_config = default_explorer_config(&self.config);
+        self.input_buffer = None;
+        self.input_history_index = None;
+        self.pending_focus = None;
+        self.pending_confirmation = None;
+        self.entered_directory = None;
+        self.suppress_refresh = false;
+        self.animations.clear();
+        self.logs.clear();
+        self.mode = self.initial_mode.clone();
+        self.last_pwd = None;
+
+        // Not via `change_directory`, that may explore on its own.
+        if PathBuf::from(&self.initial_pwd).is_dir() {
+            self.pwd = self.initial_pwd.clone();
+        };
+        self.explore()
+    }
+
+    fn self_check_and_log(mut self) -> Result<Self> {
+        let problems = self.validate();
+        if problems.is_empty() {
+            return self.log_info("self check found no inconsistencies".into());
+        };
+        for problem in problems {
+            self = self.log_warning(format!("self check: {}", problem))?;
+        }
+        Ok(self)
+    }
+
+    fn print_relative_path_and_quit(mut self, base: String) -> Result<Self> {
+        self.msg_out
+            .push_back(MsgOut::PrintRelativePathAndQuit(base));
+        Ok(self)
+    }
+
     fn print_app_state_and_quit(mut self) -> Result<Self> {
         self.msg_out.push_back(MsgOut::PrintAppStateAndQuit);
         Ok(self)
@@ -1203,6 +3662,17 @@ impl App {
         Ok(self)
     }
 
+    fn sleep(mut self, millis: u64) -> Result<Self> {
+        let capped = millis.min(MAX_SLEEP_MILLIS);
+        self.msg_out
+            .push_back(MsgOut::Sleep(Duration::from_millis(capped)));
+        if capped < millis {
+            self.log_warning(format!("sleep of {} ms capped to {} ms", millis, capped))
+        } else {
+            self.log_info(format!("sleeping for {} ms", capped))
+        }
+    }
+
     fn directory_buffer_mut(&mut self) -> Option<&mut DirectoryBuffer> {
         self.directory_buffers.get_mut(&self.pwd)
     }
@@ -1212,6 +3682,11 @@ impl App {
         &self.pwd
     }
 
+    /// Get a reference to the app's previously visited directory.
+    pub fn last_pwd(&self) -> Option<&String> {
+        self.last_pwd.as_ref()
+    }
+
     /// Get a reference to the app's current directory buffer.
     pub fn directory_buffer(&self) -> Option<&DirectoryBuffer> {
         self.directory_buffers.get(&self.pwd)
@@ -1227,10 +3702,30 @@ impl App {
         &self.selection
     }
 
+    /// Get the selected nodes grouped by their parent directories, e.g. to run
+    /// a command once per directory.
+    pub fn selection_grouped_by_parent(&self) -> BTreeMap<String, Vec<&Node>> {
+        let mut groups: BTreeMap<String, Vec<&Node>> = BTreeMap::new();
+        for node in self.selection.iter() {
+            groups.entry(node.parent.clone()).or_default().push(node);
+        }
+        groups
+    }
+
     pub fn pop_msg_out(&mut self) -> Option<MsgOut> {
         self.msg_out.pop_front()
     }
 
+    /// Get a reference to the next output message, without consuming it.
+    pub fn peek_msg_out(&self) -> Option<&MsgOut> {
+        self.msg_out.front()
+    }
+
+    /// Get the number of pending output messages.
+    pub fn msg_out_len(&self) -> usize {
+        self.msg_out.len()
+    }
+
     /// Get a reference to the app's mode.
     pub fn mode(&self) -> &Mode {
         &self.mode
@@ -1241,11 +3736,31 @@ impl App {
         &self.directory_buffers
     }
 
+    /// Get a reference to the app's last known focus per directory.
+    pub fn last_focus(&self) -> &HashMap<String, usize> {
+        &self.last_focus
+    }
+
     /// Get a reference to the app's input buffer.
     pub fn input_buffer(&self) -> Option<String> {
         self.input_buffer.clone()
     }
 
+    /// Get the destructive operation waiting for a `ConfirmPending`.
+    pub fn pending_confirmation(&self) -> Option<&ExternalMsg> {
+        self.pending_confirmation.as_ref()
+    }
+
+    /// Get the index of the node set as the anchor for `SelectToAnchor`.
+    pub fn selection_anchor(&self) -> Option<usize> {
+        self.selection_anchor
+    }
+
+    /// Get a reference to the app's input history, the oldest first.
+    pub fn input_history(&self) -> &VecDeque<String> {
+        &self.input_history
+    }
+
     /// Get a reference to the app's pipes.
     pub fn pipe(&self) -> &Pipe {
         &self.pipe
@@ -1271,27 +3786,205 @@ impl App {
         Ok(self)
     }
 
+    /// Get the selection, without the nodes that no longer exist if
+    /// `auto_prune_selection` is set.
+    pub fn pruned_selection(&self) -> Vec<&Node> {
+        // Same as calling `refresh_selection` first, without needing to mutate.
+        if self.config.general.auto_prune_selection {
+            self.selection
+                .iter()
+                .filter(|n| PathBuf::from(&n.absolute_path).exists())
+                .collect()
+        } else {
+            self.selection.iter().collect()
+        }
+    }
+
     pub fn result(&self) -> Vec<&Node> {
-        if self.selection.is_empty() {
+        let selection = self.pruned_selection();
+        if selection.is_empty() {
             self.focused_node().map(|n| vec![n]).unwrap_or_default()
         } else {
-            self.selection.iter().map(|n| n).collect()
+            selection
         }
     }
 
     pub fn result_str(&self) -> String {
         self.result()
             .into_iter()
-            .map(|n| n.absolute_path.clone())
+            .map(|n| {
+                self.config
+                    .general
+                    .result_format
+                    .as_ref()
+                    .map(|f| n.format(f))
+                    .unwrap_or_else(|| {
+                        if self.config.general.result_uses_path_display {
+                            self.display_path(n)
+                        } else {
+                            n.absolute_path.clone()
+                        }
+                    })
+            })
             .collect::<Vec<String>>()
             .join("\n")
     }
 
+    pub fn relative_result_str(&self, base: &String) -> String {
+        let base = PathBuf::from(base);
+        self.result()
+            .into_iter()
+            .map(|n| {
+                relative_path(&PathBuf::from(&n.absolute_path), &base)
+                    .map(|p| p.to_string_lossy().to_string())
+                    .unwrap_or_else(|| n.absolute_path.clone())
+            })
+            .collect::<Vec<String>>()
+            .join("\n")
+    }
+
+    /// Find the inconsistencies in the state, e.g. a focus out of range, or a
+    /// selected path that no longer exists. The directories are checked in
+    /// order, then the selection.
+    pub fn validate(&self) -> Vec<String> {
+        let mut problems = vec![];
+
+        if !PathBuf::from(&self.pwd).is_dir() {
+            problems.push(format!("pwd {} is not a directory", &self.pwd));
+        };
+
+        let mut parents = self.directory_buffers.keys().collect::<Vec<&String>>();
+        parents.sort();
+        for parent in parents {
+            let dir = &self.directory_buffers[parent];
+            if dir.focus >= dir.nodes.len().max(1) {
+                problems.push(format!(
+                    "focus={} but total={} in {}",
+                    dir.focus,
+                    dir.nodes.len(),
+                    parent
+                ));
+            };
+        }
+
+        let mut seen = HashSet::new();
+        for node in self.selection.iter() {
+            let path = PathBuf::from(&node.absolute_path);
+            if !seen.insert(&node.absolute_path) {
+                problems.push(format!(
+                    "{} is selected more than once",
+                    &node.absolute_path
+                ));
+            };
+
+            match path.canonicalize() {
+                Err(_) => problems.push(format!("selected {} doesn't exist", &node.absolute_path)),
+                Ok(canonical)
+                    if self.explorer_config.symlink_resolution == SymlinkResolution::Follow
+                        && canonical != path =>
+                {
+                    problems.push(format!(
+                        "selected {} resolves to {}",
+                        &node.absolute_path,
+                        canonical.to_string_lossy()
+                    ))
+                }
+                Ok(_) => {}
+            };
+        }
+
+        problems
+    }
+
+    /// Get the path of the node to display, as per `general.path_display`.
+    pub fn display_path(&self, node: &Node) -> String {
+        let home = dirs::home_dir().map(|h| h.to_string_lossy().to_string());
+        node.display_path(self.config.general.path_display, &self.pwd, home.as_deref())
+    }
+
+    /// Get the focused path, or null, and the selected paths, in JSON format.
+    pub fn focused_and_selection_str(&self) -> String {
+        serde_json::json!({
+            "focused": self.focused_node().map(|n| &n.absolute_path),
+            "selection": self
+                .selection
+                .iter()
+                .map(|n| &n.absolute_path)
+                .collect::<Vec<&String>>(),
+        })
+        .to_string()
+    }
+
+    /// Get the focused node with its metadata, in JSON format.
+    pub fn focused_metadata_str(&self) -> Option<String> {
+        self.focused_node()
+            .cloned()
+            .map(NodeMetadata::new)
+            .and_then(|m| serde_json::to_string(&m).ok())
+    }
+
+    /// Summarize the selection. The file sizes are read from the disk.
+    pub fn selection_stats(&self) -> SelectionStats {
+        self.selection
+            .iter()
+            .fold(SelectionStats::default(), |mut stats, node| {
+                stats.count += 1;
+                if node.is_dir {
+                    stats.directories += 1;
+                } else if node.is_file {
+                    stats.files += 1;
+                    stats.total_bytes += fs::metadata(&node.absolute_path)
+                        .map(|m| m.len())
+                        .unwrap_or_default();
+                };
+                if node.is_symlink {
+                    stats.symlinks += 1;
+                };
+                stats
+            })
+    }
+
+    /// Get the selection stats, in JSON format.
+    pub fn selection_stats_str(&self) -> String {
+        serde_json::to_string(&self.selection_stats()).unwrap_or_default()
+    }
+
+    /// Count the nodes in the present working directory that would pass the given
+    /// filter, ignoring the filters currently applied. Returns (passing, total).
+    pub fn preview_filter_impact(&self, filter: &NodeFilterApplicable) -> (usize, usize) {
+        self.directory_buffer()
+            .map(|d| {
+                let passing = d.all_nodes.iter().filter(|n| filter.apply(n)).count();
+                (passing, d.all_nodes.len())
+            })
+            .unwrap_or_default()
+    }
+
     /// Get a reference to the app's explorer config.
     pub fn explorer_config(&self) -> &ExplorerConfig {
         &self.explorer_config
     }
 
+    /// Get a reference to the app's screen layout, as last reported by the UI.
+    pub fn layout(&self) -> &ScreenLayout {
+        &self.layout
+    }
+
+    /// Get the app's current random seed.
+    pub fn random_seed(&self) -> u64 {
+        self.random_seed
+    }
+
+    /// Whether the app is in the middle of a batch, suppressing refreshes.
+    pub fn suppress_refresh(&self) -> bool {
+        self.suppress_refresh
+    }
+
+    /// Whether the present working directory is being shown as a flat tree.
+    pub fn exploring_recursively(&self) -> bool {
+        self.exploring_recursively
+    }
+
     /// Get a reference to the app's logs.
     pub fn logs(&self) -> &Vec<Log> {
         &self.logs
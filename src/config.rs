@@ -1,5 +1,8 @@
+use crate::app::DefaultExplorerConfig;
 use crate::app::ExternalMsg;
 use crate::app::HelpMenuLine;
+use crate::app::PathDisplay;
+use crate::app::SymlinkResolution;
 use crate::app::VERSION;
 use serde::{Deserialize, Serialize};
 use serde_yaml;
@@ -149,6 +152,100 @@ pub struct GeneralConfig {
     #[serde(default)]
     pub show_hidden: bool,
 
+    /// The glob patterns, e.g. `*.pyc`, of the nodes to treat as hidden along
+    /// with the dotfiles. Only `*` and `?` are supported.
+    #[serde(default)]
+    pub hidden_file_patterns: Vec<String>,
+
+    #[serde(default)]
+    pub directories_first: bool,
+
+    #[serde(default)]
+    pub max_nodes: Option<usize>,
+
+    #[serde(default)]
+    pub enter_file_focuses_parent: bool,
+
+    /// Whether to log when the directory entered turns out to be empty, so
+    /// that it's clear the enter worked.
+    #[serde(default = "default_true")]
+    pub follow_focus_into_empty: bool,
+
+    #[serde(default)]
+    pub result_format: Option<String>,
+
+    #[serde(default)]
+    pub max_selection: Option<usize>,
+
+    /// Whether to focus the only file left in the present working directory
+    /// after exploring or entering it, e.g. after narrowing it down with
+    /// filters. It happens once per exploration, not on every refresh.
+    #[serde(default)]
+    pub auto_select_on_enter_single: bool,
+
+    /// The messages to handle once `auto_select_on_enter_single` focused the
+    /// only file, e.g. `[Select]` or `[PrintResultAndQuit]`.
+    #[serde(default)]
+    pub auto_select_on_enter_single_messages: Vec<ExternalMsg>,
+
+    #[serde(default)]
+    pub start_mode: Option<String>,
+
+    /// Whether the focus should move along with the focused node when the
+    /// nodes are sorted.
+    #[serde(default = "default_true")]
+    pub focus_follows_sort: bool,
+
+    /// Whether the result should leave out the selected nodes that no longer
+    /// exist. It needs to stat every selected node.
+    #[serde(default)]
+    pub auto_prune_selection: bool,
+
+    /// Whether the destructive messages, e.g. `RenameSelectionWithTemplate`,
+    /// should wait for a `ConfirmPending` instead of acting immediately.
+    #[serde(default)]
+    pub confirm_destructive: bool,
+
+    #[serde(default)]
+    pub symlink_resolution: SymlinkResolution,
+
+    /// How to display the paths of the nodes in the UI.
+    #[serde(default)]
+    pub path_display: PathDisplay,
+
+    /// Whether the printed result should also use `path_display`, unless
+    /// `result_format` is set. The scripts get the absolute paths either way.
+    #[serde(default)]
+    pub result_uses_path_display: bool,
+
+    /// Whether to enter the symlinked directories through the link, like the
+    /// other directories, instead of jumping to the resolved target, and to
+    /// descend into them while exploring recursively. They are still displayed
+    /// as symlinks.
+    #[serde(default)]
+    pub explore_symlinks_as_dirs: bool,
+
+    /// The filters and sorters to explore with by default, also restored by
+    /// `ResetNodeFilters`. When set, `show_hidden` is ignored, but the
+    /// `hidden_file_patterns` are still filtered out.
+    #[serde(default)]
+    pub default_explorer_config: Option<DefaultExplorerConfig>,
+
+    /// Whether to explore the directory again every time it's changed into,
+    /// instead of showing the buffer loaded before. The listing is then always
+    /// up to date, but every navigation reads the directory (and its parents)
+    /// from the disk, which can be slow on big or remote directories.
+    #[serde(default)]
+    pub explore_on_change_directory: bool,
+
+    /// How many of the used inputs to remember for `RecallPreviousInput`.
+    #[serde(default = "default_input_history_size")]
+    pub input_history_size: usize,
+
+    /// How often to tick the animations, in milliseconds. No ticks if not set.
+    #[serde(default)]
+    pub tick_interval: Option<u64>,
+
     #[serde(default)]
     pub table: TableConfig,
 
@@ -162,10 +259,43 @@ pub struct GeneralConfig {
     pub selection_ui: UIConfig,
 }
 
+fn default_true() -> bool {
+    true
+}
+
+fn default_input_history_size() -> usize {
+    100
+}
+
 impl Default for GeneralConfig {
     fn default() -> Self {
         let yaml = r###"
           show_hidden: false
+          hidden_file_patterns: []
+          directories_first: false
+          max_nodes: null
+          enter_file_focuses_parent: false
+          follow_focus_into_empty: true
+          result_format: null
+          max_selection: null
+          auto_select_on_enter_single: false
+          auto_select_on_enter_single_messages: []
+          start_mode: null
+          focus_follows_sort: true
+          auto_prune_selection: false
+          confirm_destructive: false
+          symlink_resolution: Follow
+          explore_symlinks_as_dirs: false
+          path_display: Absolute
+          result_uses_path_display: false
+          default_explorer_config:
+            filters:
+              - filter: RelativePathDoesNotStartWith
+                input: "."
+            sorters: []
+          explore_on_change_directory: false
+          input_history_size: 100
+          tick_interval: null
           table:
             header:
               cols:
@@ -180,7 +310,7 @@ impl Default for GeneralConfig {
                   bits: 0
             row:
               cols:
-              - format: "{{{tree}}}{{{prefix}}}{{{icon}}} {{{relativePath}}}{{#if isDir}}/{{/if}}{{{suffix}}}"
+              - format: "{{{tree}}}{{{prefix}}}{{{icon}}} {{{relativePath}}}{{#if isDir}}/{{/if}}{{#if symlinkTarget}} -> {{{symlinkTarget}}}{{/if}}{{{suffix}}}"
               - format: "{{{mimeEssence}}}"
               - format: "{{#if isBeforeFocus}}-{{else}} {{/if}}{{{relativeIndex}}}/{{{index}}}/{{{total}}}"
 
@@ -241,6 +371,24 @@ pub struct KeyBindings {
     pub default: Option<Action>,
 }
 
+impl KeyBindings {
+    /// Get all the actions, with the names of the bindings they are bound to.
+    pub fn actions(&self) -> Vec<(String, &Action)> {
+        self.on_key
+            .iter()
+            .map(|(k, a)| (format!("on_key.{}", k), a))
+            .chain(self.on_alphabet.iter().map(|a| ("on_alphabet".into(), a)))
+            .chain(self.on_number.iter().map(|a| ("on_number".into(), a)))
+            .chain(
+                self.on_special_character
+                    .iter()
+                    .map(|a| ("on_special_character".into(), a)),
+            )
+            .chain(self.default.iter().map(|a| ("default".into(), a)))
+            .collect()
+    }
+}
+
 impl Default for KeyBindings {
     fn default() -> Self {
         let on_key: BTreeMap<String, Action> = serde_yaml::from_str(
@@ -341,9 +489,7 @@ impl Default for KeyBindings {
               ".":
                 help: show hidden
                 messages:
-                  - ToggleNodeFilter:
-                      filter: RelativePathDoesNotStartWith
-                      input: .
+                  - ToggleHidden
                   - Explore
 
               enter:
@@ -419,6 +565,10 @@ pub struct Mode {
 
     #[serde(default)]
     pub key_bindings: KeyBindings,
+
+    /// Clear the logs when switching into this mode.
+    #[serde(default)]
+    pub clear_logs_on_enter: bool,
 }
 
 impl Mode {
@@ -493,6 +643,7 @@ impl Default for Mode {
             help: Default::default(),
             extra_help: Default::default(),
             key_bindings: Default::default(),
+            clear_logs_on_enter: Default::default(),
         }
     }
 }
@@ -511,6 +662,43 @@ pub struct Config {
     pub modes: HashMap<String, Mode>,
 }
 
+impl Config {
+    /// Find the mistakes in the key bindings that serde can't catch, e.g.
+    /// switching to a mode that doesn't exist, or calling an empty command.
+    pub fn validate(&self) -> Vec<String> {
+        let mut names = self.modes.keys().collect::<Vec<&String>>();
+        names.sort();
+
+        let mut problems = vec![];
+        for name in names {
+            for (binding, action) in self.modes[name].key_bindings.actions() {
+                for msg in action.messages.iter() {
+                    let problem = match msg {
+                        ExternalMsg::SwitchMode(m) | ExternalMsg::SwitchModeKeepingInput(m)
+                            if !self.modes.contains_key(m) =>
+                        {
+                            Some(format!("switches to unknown mode {}", m))
+                        }
+                        ExternalMsg::Call(c)
+                        | ExternalMsg::WriteSelectionToTempFileAndCall(c)
+                        | ExternalMsg::WriteNulSeparatedSelectionToTempFileAndCall(c)
+                            if c.command.trim().is_empty() =>
+                        {
+                            Some("calls an empty command".to_string())
+                        }
+                        _ => None,
+                    };
+
+                    if let Some(problem) = problem {
+                        problems.push(format!("mode {}, {}: {}", name, binding, problem));
+                    };
+                }
+            }
+        }
+        problems
+    }
+}
+
 impl Default for Config {
     fn default() -> Self {
         let search_mode: Mode = serde_yaml::from_str(
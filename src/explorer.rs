@@ -1,9 +1,213 @@
 use crate::app::{DirectoryBuffer, ExplorerConfig, ExternalMsg, InternalMsg, MsgIn, Node, Task};
+use anyhow::Result;
 use std::fs;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 use std::sync::mpsc::Sender;
 use std::thread;
 
+pub fn explore_sync(
+    config: ExplorerConfig,
+    parent: String,
+    focused_path: Option<String>,
+) -> Result<DirectoryBuffer> {
+    let path = PathBuf::from(&parent);
+    let dirs = fs::read_dir(&path)?;
+
+    let names = dirs.filter_map(|d| {
+        d.ok().map(|e| {
+            e.path()
+                .file_name()
+                .map(|n| n.to_string_lossy().to_string())
+                .unwrap_or_default()
+        })
+    });
+
+    // Once the cap is reached, the remaining entries are only counted, not
+    // loaded (and hence, not filtered).
+    let mut all_nodes = vec![];
+    let mut skipped = 0;
+    for name in names {
+        if config
+            .max_nodes()
+            .map(|m| all_nodes.len() >= m)
+            .unwrap_or(false)
+        {
+            skipped += 1;
+        } else {
+            all_nodes.push(Node::with_symlink_resolution(
+                parent.clone(),
+                name,
+                config.symlink_resolution(),
+            ));
+        };
+    }
+
+    let mut nodes = all_nodes
+        .iter()
+        .filter(|n| config.apply(n))
+        .cloned()
+        .collect::<Vec<Node>>();
+
+    config.sort(&mut nodes);
+
+    if config.directories_first() {
+        // Each group is ordered by the sorters, then by the node order.
+        nodes.sort_by(|a, b| {
+            b.is_dir
+                .cmp(&a.is_dir)
+                .then_with(|| config.compare(a, b))
+                .then_with(|| a.cmp(b))
+        });
+    };
+
+    let focus_index = focused_path.and_then(|focus| {
+        nodes
+            .iter()
+            .enumerate()
+            .find(|(_, n)| n.relative_path == focus)
+            .map(|(i, _)| i)
+    });
+
+    let mut dir = DirectoryBuffer::new(parent, nodes, all_nodes, focus_index.unwrap_or(0));
+    dir.raw_total += skipped;
+    dir.focused_by_name = focus_index.is_some();
+    Ok(dir)
+}
+
+struct RecursiveWalker {
+    config: ExplorerConfig,
+    root: PathBuf,
+    max_depth: Option<usize>,
+    all_nodes: Vec<Node>,
+    nodes: Vec<Node>,
+    skipped: usize,
+    /// The real paths of the directories being walked, to not follow a
+    /// symlink into a cycle.
+    ancestors: Vec<PathBuf>,
+}
+
+impl RecursiveWalker {
+    fn walk(&mut self, relative: &Path, depth: usize) -> Result<()> {
+        let dir = self.root.join(relative);
+        let dir_str = dir.to_string_lossy().to_string();
+
+        for entry in fs::read_dir(&dir)?.filter_map(|e| e.ok()) {
+            if self
+                .config
+                .max_nodes()
+                .map(|m| self.all_nodes.len() >= m)
+                .unwrap_or(false)
+            {
+                self.skipped += 1;
+                continue;
+            };
+
+            let name = entry.file_name().to_string_lossy().to_string();
+            let relative_path = relative.join(&name);
+
+            // The filters see the node as if its directory was being explored,
+            // so that e.g. the hidden nodes are filtered at every level.
+            let mut node = Node::with_symlink_resolution(
+                dir_str.clone(),
+                name,
+                self.config.symlink_resolution(),
+            );
+            let passes = self.config.apply(&node);
+            let descend = passes
+                && node.is_dir
+                && (!node.is_symlink || self.config.explore_symlinks_as_dirs())
+                && self.max_depth.map(|m| depth < m).unwrap_or(true);
+
+            node.relative_path = relative_path.to_string_lossy().to_string();
+
+            self.all_nodes.push(node.clone());
+            if passes {
+                self.nodes.push(node);
+            };
+
+            if descend {
+                if let Ok(real) = self.root.join(&relative_path).canonicalize() {
+                    if !self.ancestors.contains(&real) {
+                        self.ancestors.push(real);
+                        // The unreadable subdirectories are skipped.
+                        self.walk(&relative_path, depth + 1).unwrap_or_default();
+                        self.ancestors.pop();
+                    };
+                };
+            };
+        }
+        Ok(())
+    }
+}
+
+/// Explore the directory and its subdirectories up to the given depth into a
+/// flat buffer, with the relative paths of the nodes relative to the explored
+/// directory. The parents of the nodes stay the directories they are in.
+/// The direct children are at depth 1. Filtered out directories are not
+/// descended into, and `directories_first` is ignored to keep the tree order.
+/// Once the cap is reached, the remaining entries in the directories being
+/// read are only counted. The symlinked directories are descended into only
+/// with `explore_symlinks_as_dirs`, and never back into a directory being
+/// walked.
+pub fn explore_recursive_sync(
+    config: ExplorerConfig,
+    parent: String,
+    max_depth: Option<usize>,
+) -> Result<DirectoryBuffer> {
+    let mut walker = RecursiveWalker {
+        config,
+        root: PathBuf::from(&parent),
+        max_depth,
+        all_nodes: vec![],
+        nodes: vec![],
+        skipped: 0,
+        ancestors: PathBuf::from(&parent).canonicalize().into_iter().collect(),
+    };
+    walker.walk(Path::new(""), 1)?;
+
+    let mut dir = DirectoryBuffer::new(parent, walker.nodes, walker.all_nodes, 0);
+    dir.raw_total += walker.skipped;
+    Ok(dir)
+}
+
+fn send_directory(tx: &Sender<Task>, parent: String, result: Result<DirectoryBuffer>) {
+    let dir = result.unwrap_or_else(|e| {
+        tx.send(Task::new(
+            1,
+            MsgIn::External(ExternalMsg::LogError(format!(
+                "failed to explore {}: {}",
+                &parent, e
+            ))),
+            None,
+        ))
+        .unwrap();
+
+        // Still add an empty buffer to keep the UI consistent.
+        DirectoryBuffer::new(parent.clone(), vec![], vec![], 0)
+    });
+
+    if dir.is_truncated() {
+        tx.send(Task::new(
+            1,
+            MsgIn::External(ExternalMsg::LogWarning(format!(
+                "{} was truncated, showing {} of {} nodes",
+                &parent,
+                dir.all_nodes.len(),
+                dir.raw_total
+            ))),
+            None,
+        ))
+        .unwrap();
+    };
+
+    tx.send(Task::new(
+        1,
+        MsgIn::Internal(InternalMsg::AddDirectory(parent, dir)),
+        None,
+    ))
+    .unwrap();
+}
+
 pub fn explore(
     config: ExplorerConfig,
     parent: String,
@@ -16,49 +220,8 @@ pub fn explore(
     let config_cloned = config.clone();
 
     thread::spawn(move || {
-        fs::read_dir(&path)
-            .map(|dirs| {
-                dirs.filter_map(|d| {
-                    d.ok().map(|e| {
-                        e.path()
-                            .file_name()
-                            .map(|n| n.to_string_lossy().to_string())
-                            .unwrap_or_default()
-                    })
-                })
-                .map(|name| Node::new(parent.clone(), name))
-                .filter(|n| config.apply(n))
-                .collect::<Vec<Node>>()
-            })
-            .map(|nodes| {
-                let focus_index = if let Some(focus) = focused_path {
-                    nodes
-                        .iter()
-                        .enumerate()
-                        .find(|(_, n)| n.relative_path == focus)
-                        .map(|(i, _)| i)
-                        .unwrap_or(0)
-                } else {
-                    0
-                };
-
-                let dir = DirectoryBuffer::new(parent.clone(), nodes, focus_index);
-
-                tx.send(Task::new(
-                    1,
-                    MsgIn::Internal(InternalMsg::AddDirectory(parent, dir)),
-                    None,
-                ))
-                .unwrap();
-            })
-            .unwrap_or_else(|e| {
-                tx.send(Task::new(
-                    1,
-                    MsgIn::External(ExternalMsg::LogError(e.to_string())),
-                    None,
-                ))
-                .unwrap();
-            })
+        let result = explore_sync(config, parent.clone(), focused_path);
+        send_directory(&tx, parent, result);
     });
 
     if let Some(grand_parent) = path_cloned.parent() {
@@ -72,3 +235,15 @@ pub fn explore(
         );
     }
 }
+
+pub fn explore_recursive(
+    config: ExplorerConfig,
+    parent: String,
+    max_depth: Option<usize>,
+    tx: Sender<Task>,
+) {
+    thread::spawn(move || {
+        let result = explore_recursive_sync(config, parent.clone(), max_depth);
+        send_directory(&tx, parent, result);
+    });
+}
@@ -5,3 +5,4 @@ pub mod input;
 pub mod ui;
 pub mod pipe_reader;
 pub mod event_reader;
+pub mod ticker;
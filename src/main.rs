@@ -6,6 +6,8 @@ use std::fs;
 use std::io::prelude::*;
 use std::path::PathBuf;
 use std::sync::mpsc;
+use std::thread;
+use std::time::Duration;
 use termion::get_tty;
 use tui::backend::CrosstermBackend;
 use tui::Terminal;
@@ -13,6 +15,7 @@ use xplr::app;
 use xplr::event_reader;
 use xplr::explorer;
 use xplr::pipe_reader;
+use xplr::ticker;
 use xplr::ui;
 
 fn main() -> Result<()> {
@@ -60,12 +63,54 @@ fn main() -> Result<()> {
 
     pipe_reader::keep_reading(app.pipe().msg_in.clone(), tx_msg_in.clone());
 
+    if let Some(interval) = app.config().general.tick_interval {
+        ticker::keep_ticking(Duration::from_millis(interval), tx_msg_in.clone());
+    };
+
     let (tx_event_reader, rx_event_reader) = mpsc::channel();
     event_reader::keep_reading(tx_msg_in.clone(), rx_event_reader);
 
     let mut last_pwd = app.pwd().clone();
+    let mut last_call_selection: Vec<String> = vec![];
+    let mut selection_files = 0;
     'outer: while result.is_ok() {
         while let Some(msg) = app.pop_msg_out() {
+            let mut selection_file = None;
+            let msg = match msg {
+                app::MsgOut::CallWithSelectionFile {
+                    command,
+                    nul_separated,
+                } => {
+                    // A fresh file for every call, so that they don't
+                    // overwrite each other.
+                    selection_files += 1;
+                    let path = PathBuf::from(app.session_path())
+                        .join(format!("selection-{}", selection_files))
+                        .to_string_lossy()
+                        .to_string();
+
+                    let selection = app
+                        .pruned_selection()
+                        .iter()
+                        .map(|n| n.absolute_path.clone())
+                        .collect::<Vec<String>>()
+                        .join(if nul_separated { "\0" } else { "\n" });
+
+                    if let Err(e) = fs::write(&path, selection) {
+                        let msg = app::MsgIn::External(app::ExternalMsg::LogError(format!(
+                            "failed to write selection file {}: {}",
+                            path, e
+                        )));
+                        tx_msg_in.send(app::Task::new(1, msg, None))?;
+                        continue;
+                    };
+                    let cmd = command.with_selection_file(&path);
+                    selection_file = Some(path);
+                    app::MsgOut::Call(cmd)
+                }
+                msg => msg,
+            };
+
             match msg {
                 app::MsgOut::Debug(path) => {
                     fs::write(&path, serde_yaml::to_string(&app)?)?;
@@ -76,12 +121,46 @@ fn main() -> Result<()> {
                     break 'outer;
                 }
 
+                app::MsgOut::PrintRelativePathAndQuit(base) => {
+                    output = Some(app.relative_result_str(&base));
+                    break 'outer;
+                }
+
+                app::MsgOut::PrintFocusedMetadataAndQuit => {
+                    output = app.focused_metadata_str();
+                    break 'outer;
+                }
+
+                app::MsgOut::PrintPwdAndQuit => {
+                    output = Some(app.pwd().clone());
+                    break 'outer;
+                }
+
+                app::MsgOut::PrintSelectionStatsAndQuit => {
+                    output = Some(app.selection_stats_str());
+                    break 'outer;
+                }
+
+                app::MsgOut::PrintFocusedAndSelectionAndQuit => {
+                    output = Some(app.focused_and_selection_str());
+                    break 'outer;
+                }
+
                 app::MsgOut::PrintAppStateAndQuit => {
                     let out = serde_yaml::to_string(&app)?;
                     output = Some(out);
                     break 'outer;
                 }
 
+                app::MsgOut::Sleep(duration) => {
+                    thread::sleep(duration);
+                    let msg = app::MsgIn::External(app::ExternalMsg::LogInfo(format!(
+                        "slept for {} ms",
+                        duration.as_millis()
+                    )));
+                    tx_msg_in.send(app::Task::new(1, msg, None))?;
+                }
+
                 app::MsgOut::ClearScreen => {
                     terminal.clear()?;
                 }
@@ -90,7 +169,18 @@ fn main() -> Result<()> {
                     explorer::explore(
                         app.explorer_config().clone(),
                         app.pwd().clone(),
-                        app.focused_node().map(|n| n.relative_path.clone()),
+                        app.explore_focus(),
+                        tx_msg_in.clone(),
+                    );
+                    // No need to explore it again on the next refresh.
+                    last_pwd = app.pwd().to_owned();
+                }
+
+                app::MsgOut::ExploreRecursive(max_depth) => {
+                    explorer::explore_recursive(
+                        app.explorer_config().clone(),
+                        app.pwd().clone(),
+                        max_depth,
                         tx_msg_in.clone(),
                     );
                 }
@@ -100,7 +190,7 @@ fn main() -> Result<()> {
                         explorer::explore(
                             app.explorer_config().clone(),
                             app.pwd().clone(),
-                            app.focused_node().map(|n| n.relative_path.clone()),
+                            app.explore_focus(),
                             tx_msg_in.clone(),
                         );
                         last_pwd = app.pwd().to_owned();
@@ -109,6 +199,16 @@ fn main() -> Result<()> {
                     // UI
                     terminal.draw(|f| ui::draw(f, &app, &hb))?;
 
+                    let layout = ui::screen_layout(terminal.size()?, &app);
+                    if &layout != app.layout() {
+                        let msg = app::MsgIn::Internal(app::InternalMsg::SetLayout {
+                            rows: layout.rows,
+                            cols: layout.cols,
+                            list_height: layout.list_height,
+                        });
+                        tx_msg_in.send(app::Task::new(0, msg, None))?;
+                    };
+
                     // Pipes
                     let focused = app
                         .focused_node()
@@ -128,9 +228,18 @@ fn main() -> Result<()> {
 
                     fs::write(&app.pipe().selection_out, selection)?;
 
-                    fs::write(&app.pipe().mode_out, &app.mode().name)?;
+                    if let Err(e) = app.pipe().write_mode_out(&app.mode().name) {
+                        let msg = app::MsgIn::External(app::ExternalMsg::LogError(format!(
+                            "failed to write {}: {}",
+                            &app.pipe().mode_out,
+                            e
+                        )));
+                        tx_msg_in.send(app::Task::new(1, msg, None))?;
+                    };
                 }
 
+                app::MsgOut::CallWithSelectionFile { .. } => {}
+
                 app::MsgOut::Call(cmd) => {
                     tx_event_reader.send(true)?;
                     terminal.clear()?;
@@ -152,12 +261,15 @@ fn main() -> Result<()> {
                         .unwrap_or_default()
                         .to_string();
 
-                    let selection = app
-                        .selection()
+                    let selection_paths = app
+                        .pruned_selection()
                         .iter()
                         .map(|n| n.absolute_path.clone())
-                        .collect::<Vec<String>>()
-                        .join("\n");
+                        .collect::<Vec<String>>();
+
+                    let (selection_added, selection_removed) =
+                        app::selection_diff(&last_call_selection, &selection_paths);
+                    let selection = selection_paths.join("\n");
 
                     let directory_nodes = app
                         .directory_buffer()
@@ -205,21 +317,40 @@ fn main() -> Result<()> {
 
                     let app_yaml = serde_yaml::to_string(&app)?;
                     let session_path = app.session_path();
-                    let result = app.result_str();
+                    // Unlike the printed result, keep it plain paths for the scripts.
+                    let result = app
+                        .result()
+                        .iter()
+                        .map(|n| n.absolute_path.clone())
+                        .collect::<Vec<String>>()
+                        .join("\n");
 
-                    let status = std::process::Command::new(cmd.command.clone())
+                    let mut command = std::process::Command::new(cmd.command.clone());
+                    command
                         .current_dir(app.pwd())
                         .env("XPLR_PID", pid)
                         .env("XPLR_INPUT_BUFFER", input_buffer)
                         .env("XPLR_FOCUS_PATH", focus_path)
                         .env("XPLR_FOCUS_INDEX", focus_index)
-                        .env("XPLR_SELECTION", selection)
                         .env("XPLR_SESSION_PATH", session_path)
                         .env("XPLR_PIPE_MSG_IN", pipe_msg_in)
                         .env("XPLR_PIPE_SELECTION_OUT", pipe_selection_out)
-                        .env("XPLR_PIPE_FOCUS_OUT", pipe_focus_out)
-                        .env("XPLR_APP_YAML", app_yaml)
-                        .env("XPLR_RESULT", result)
+                        .env("XPLR_PIPE_FOCUS_OUT", pipe_focus_out);
+
+                    // A large selection would exceed the size limit of the
+                    // environment variables, that's what the file is for.
+                    // The diff is relative to the last call that got it.
+                    if selection_file.is_none() {
+                        last_call_selection = selection_paths;
+                        command
+                            .env("XPLR_SELECTION", selection)
+                            .env("XPLR_SELECTION_ADDED", selection_added.join("\n"))
+                            .env("XPLR_SELECTION_REMOVED", selection_removed.join("\n"))
+                            .env("XPLR_APP_YAML", app_yaml)
+                            .env("XPLR_RESULT", result);
+                    };
+
+                    let status = command
                         .env("XPLR_GLOBAL_HELP_MENU", help_menu)
                         .env("XPLR_DIRECTORY_NODES", directory_nodes)
                         .env("XPLR_LOGS", logs)
@@ -239,6 +370,16 @@ fn main() -> Result<()> {
                         tx_msg_in.send(app::Task::new(1, msg, None))?;
                     };
 
+                    if let Some(path) = selection_file {
+                        if let Err(e) = fs::remove_file(&path) {
+                            let msg = app::MsgIn::External(app::ExternalMsg::LogError(format!(
+                                "failed to remove {}: {}",
+                                path, e
+                            )));
+                            tx_msg_in.send(app::Task::new(1, msg, None))?;
+                        };
+                    };
+
                     terminal.hide_cursor()?;
                     execute!(terminal.backend_mut(), term::EnterAlternateScreen)?;
                     term::enable_raw_mode()?;
@@ -269,9 +410,16 @@ fn main() -> Result<()> {
 
     fs::remove_dir_all(app.session_path())?;
 
-    if let Some(out) = output {
+    // Nothing to print, e.g. the result in an empty directory. Not even a
+    // blank line.
+    if let Some(out) = output.filter(|o| !o.is_empty()) {
         println!("{}", out);
     }
 
-    result
+    match result {
+        Err(e) if e.downcast_ref::<app::AppError>() == Some(&app::AppError::Terminated) => {
+            std::process::exit(1)
+        }
+        result => result,
+    }
 }
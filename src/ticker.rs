@@ -0,0 +1,16 @@
+use crate::app::{InternalMsg, MsgIn, Task};
+use std::sync::mpsc::Sender;
+use std::thread;
+use std::time::Duration;
+
+pub fn keep_ticking(interval: Duration, tx: Sender<Task>) {
+    thread::spawn(move || loop {
+        thread::sleep(interval);
+        if tx
+            .send(Task::new(3, MsgIn::Internal(InternalMsg::Tick), None))
+            .is_err()
+        {
+            break;
+        };
+    });
+}
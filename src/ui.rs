@@ -27,8 +27,11 @@ struct NodeUIMetadata {
     pub is_file: bool,
     pub is_readonly: bool,
     pub mime_essence: String,
+    pub symlink_target: Option<String>,
+    pub icon_key: String,
 
     // Extra
+    pub display_path: String,
     pub index: usize,
     pub relative_index: usize,
     pub is_before_focus: bool,
@@ -56,6 +59,7 @@ impl NodeUIMetadata {
         is_selected: bool,
         is_focused: bool,
         total: usize,
+        display_path: String,
     ) -> Self {
         Self {
             parent: node.parent.clone(),
@@ -67,6 +71,9 @@ impl NodeUIMetadata {
             is_file: node.is_file,
             is_readonly: node.is_readonly,
             mime_essence: node.mime_essence.clone(),
+            symlink_target: node.symlink_target.clone(),
+            icon_key: node.icon_key().into(),
+            display_path,
             index,
             relative_index,
             is_before_focus,
@@ -113,7 +120,7 @@ fn draw_table<B: Backend>(f: &mut Frame<B>, rect: Rect, app: &app::App, hb: &Han
                     };
 
                     let is_first = index == 0;
-                    let is_last = index == dir.total.max(1) - 1;
+                    let is_last = index == dir.nodes.len().max(1) - 1;
 
                     let tree = config
                         .general
@@ -168,6 +175,7 @@ fn draw_table<B: Backend>(f: &mut Frame<B>, rect: Rect, app: &app::App, hb: &Han
                         is_selected,
                         is_focused,
                         dir.total,
+                        app.display_path(node),
                     );
 
                     let cols = hb
@@ -216,16 +224,25 @@ fn draw_table<B: Backend>(f: &mut Frame<B>, rect: Rect, app: &app::App, hb: &Han
         .map(|c| c.into())
         .collect();
 
+    let title = app
+        .directory_buffer()
+        .filter(|dir| dir.is_truncated())
+        .map(|dir| {
+            format!(
+                " {} (showing {} of {}) ",
+                app.pwd(),
+                dir.all_nodes.len(),
+                dir.raw_total
+            )
+        })
+        .unwrap_or_else(|| format!(" {} ", app.pwd()));
+
     let table = Table::new(rows)
         .widths(&table_constraints)
         .style(config.general.table.style)
         .highlight_style(config.general.focused_ui.style)
         .column_spacing(config.general.table.col_spacing)
-        .block(
-            Block::default()
-                .borders(Borders::ALL)
-                .title(format!(" {} ", app.pwd())),
-        );
+        .block(Block::default().borders(Borders::ALL).title(title));
 
     let table = config
         .general
@@ -247,7 +264,7 @@ fn draw_table<B: Backend>(f: &mut Frame<B>, rect: Rect, app: &app::App, hb: &Han
         .unwrap_or_else(|| table.clone());
 
     let mut table_state = TableState::default();
-    table_state.select(app.directory_buffer().map(|dir| dir.focus));
+    table_state.select(app.focused_index());
 
     f.render_stateful_widget(table, rect, &mut table_state);
 }
@@ -256,7 +273,7 @@ fn draw_selection<B: Backend>(f: &mut Frame<B>, rect: Rect, app: &app::App, _: &
     let selection: Vec<ListItem> = app
         .selection()
         .iter()
-        .map(|n| n.absolute_path.clone())
+        .map(|n| app.display_path(n))
         .map(ListItem::new)
         .collect();
 
@@ -314,6 +331,9 @@ fn draw_logs<B: Backend>(f: &mut Frame<B>, rect: Rect, app: &app::App, _: &Handl
             app::LogLevel::Info => {
                 ListItem::new(l.to_string()).style(Style::default().fg(Color::Gray))
             }
+            app::LogLevel::Warning => {
+                ListItem::new(l.to_string()).style(Style::default().fg(Color::Yellow))
+            }
             app::LogLevel::Success => {
                 ListItem::new(l.to_string()).style(Style::default().fg(Color::Green))
             }
@@ -328,6 +348,32 @@ fn draw_logs<B: Backend>(f: &mut Frame<B>, rect: Rect, app: &app::App, _: &Handl
     f.render_widget(logs_list, rect);
 }
 
+/// Get the layout `draw` would use for a screen of the given size.
+pub fn screen_layout(rect: Rect, app: &app::App) -> app::ScreenLayout {
+    let header_height = app
+        .config()
+        .general
+        .table
+        .header
+        .as_ref()
+        .map(|h| h.height)
+        .unwrap_or_default();
+
+    // The table leaves 3 rows for the input buffer or logs, and has the borders
+    // and the header to draw.
+    let list_height = rect
+        .height
+        .saturating_sub(3)
+        .saturating_sub(2)
+        .saturating_sub(header_height);
+
+    app::ScreenLayout {
+        rows: rect.height,
+        cols: rect.width,
+        list_height,
+    }
+}
+
 pub fn draw<B: Backend>(f: &mut Frame<B>, app: &app::App, hb: &Handlebars) {
     let rect = f.size();
 
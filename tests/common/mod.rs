@@ -0,0 +1,17 @@
+use std::fs;
+
+/// Create a fresh directory under the system temp dir. Names ending with `/`
+/// are created as directories, the rest as empty files.
+pub fn create_dir(name: &str, entries: &[&str]) -> String {
+    let dir = std::env::temp_dir().join("xplr_test").join(name);
+    fs::remove_dir_all(&dir).unwrap_or_default();
+    fs::create_dir_all(&dir).unwrap();
+    for entry in entries {
+        if entry.ends_with('/') {
+            fs::create_dir_all(dir.join(entry)).unwrap();
+        } else {
+            fs::File::create(dir.join(entry)).unwrap();
+        }
+    }
+    dir.canonicalize().unwrap().to_string_lossy().to_string()
+}
@@ -0,0 +1,2342 @@
+use std::fs;
+use std::path::PathBuf;
+use std::time::Duration;
+use xplr::*;
+
+mod common;
+use common::create_dir;
+
+fn handle(app: app::App, msg: app::ExternalMsg) -> app::App {
+    app.enqueue(app::Task::new(0, app::MsgIn::External(msg), None))
+        .possibly_mutate()
+        .unwrap()
+}
+
+fn handle_internal(app: app::App, msg: app::InternalMsg) -> app::App {
+    app.enqueue(app::Task::new(0, app::MsgIn::Internal(msg), None))
+        .possibly_mutate()
+        .unwrap()
+}
+
+fn directory_buffer(parent: &str, focus: usize) -> app::DirectoryBuffer {
+    let mut names = fs::read_dir(parent)
+        .unwrap()
+        .map(|e| e.unwrap().file_name().to_string_lossy().to_string())
+        .collect::<Vec<String>>();
+    names.sort();
+
+    let nodes = names
+        .into_iter()
+        .map(|n| app::Node::new(parent.into(), n))
+        .collect::<Vec<app::Node>>();
+
+    app::DirectoryBuffer::new(parent.into(), nodes.clone(), nodes, focus)
+}
+
+fn change_directory(app: app::App, parent: &str) -> app::App {
+    let app = handle(app, app::ExternalMsg::ChangeDirectory(parent.into()));
+    handle_internal(
+        app,
+        app::InternalMsg::AddDirectory(parent.into(), directory_buffer(parent, 0)),
+    )
+}
+
+#[test]
+fn test_switch_mode_keeping_input() {
+    let app = app::App::new(
+        config::Config::default(),
+        std::env::temp_dir().to_string_lossy().into(),
+    );
+    let app = handle(app, app::ExternalMsg::SetInputBuffer("foo".into()));
+
+    let app = handle(
+        app,
+        app::ExternalMsg::SwitchModeKeepingInput("search".into()),
+    );
+    assert_eq!(app.mode().name, "search");
+    assert_eq!(app.input_buffer(), Some("foo".into()));
+
+    let app = handle(app, app::ExternalMsg::SwitchMode("default".into()));
+    assert_eq!(app.mode().name, "default");
+    assert_eq!(app.input_buffer(), None);
+}
+
+#[test]
+fn test_last_focus_survives_buffer_eviction() {
+    let dir = create_dir("last_focus", &["a", "b", "c", "d"]);
+    let other = create_dir("last_focus_other", &["x"]);
+
+    let app = change_directory(app::App::new(config::Config::default(), dir.clone()), &dir);
+    let app = handle(app, app::ExternalMsg::FocusByIndex(2));
+    let app = change_directory(app, &other);
+    assert_eq!(app.last_focus().get(&dir), Some(&2));
+
+    // Evict every cached buffer.
+    let mut state = serde_yaml::to_value(&app).unwrap();
+    state["directory_buffers"] = serde_yaml::Value::Mapping(Default::default());
+    let app: app::App = serde_yaml::from_value(state).unwrap();
+    assert!(app.directory_buffers().is_empty());
+
+    let app = change_directory(app, &dir);
+    assert_eq!(app.directory_buffer().map(|d| d.focus), Some(2));
+    assert_eq!(
+        app.focused_node().map(|n| PathBuf::from(&n.absolute_path)),
+        Some(PathBuf::from(&dir).join("c"))
+    );
+}
+
+#[test]
+fn test_relative_path() {
+    use std::path::Path;
+
+    // Nested
+    assert_eq!(
+        app::relative_path(Path::new("/repo/src/app.rs"), Path::new("/repo")),
+        Some(PathBuf::from("src/app.rs"))
+    );
+
+    // Sibling
+    assert_eq!(
+        app::relative_path(Path::new("/repo/docs/a.md"), Path::new("/repo/src")),
+        Some(PathBuf::from("../docs/a.md"))
+    );
+
+    // Same
+    assert_eq!(
+        app::relative_path(Path::new("/repo"), Path::new("/repo")),
+        Some(PathBuf::from("."))
+    );
+
+    // Cross root
+    assert_eq!(
+        app::relative_path(Path::new("/repo/src"), Path::new("repo")),
+        None
+    );
+}
+
+#[test]
+fn test_print_relative_path_and_quit() {
+    let dir = create_dir("relative_result", &["a/", "b"]);
+    let app = change_directory(app::App::new(config::Config::default(), dir.clone()), &dir);
+    let app = handle(app, app::ExternalMsg::FocusByFileName("b".into()));
+
+    let mut app = handle(
+        app,
+        app::ExternalMsg::PrintRelativePathAndQuit(format!("{}/a", dir)),
+    );
+
+    let mut last = None;
+    while let Some(msg) = app.pop_msg_out() {
+        last = Some(msg);
+    }
+    assert_eq!(
+        last,
+        Some(app::MsgOut::PrintRelativePathAndQuit(format!("{}/a", dir)))
+    );
+    assert_eq!(app.relative_result_str(&format!("{}/a", dir)), "../b");
+    assert_eq!(
+        app.relative_result_str(&"relative".into()),
+        format!("{}/b", dir)
+    );
+}
+
+#[test]
+fn test_focus_by_stem() {
+    let dir = create_dir(
+        "focus_by_stem",
+        &["a.txt", "Report.md", "report.pdf", "report.txt"],
+    );
+    let app = change_directory(app::App::new(config::Config::default(), dir.clone()), &dir);
+
+    let app = handle(app, app::ExternalMsg::FocusByStem("report".into()));
+    assert_eq!(
+        app.focused_node().map(|n| n.relative_path.clone()),
+        Some("report.pdf".into())
+    );
+
+    let app = handle(app, app::ExternalMsg::FocusByStem("REPORT".into()));
+    assert_eq!(
+        app.focused_node().map(|n| n.relative_path.clone()),
+        Some("Report.md".into())
+    );
+
+    let app = handle(app, app::ExternalMsg::FocusByStem("missing".into()));
+    assert_eq!(
+        app.focused_node().map(|n| n.relative_path.clone()),
+        Some("Report.md".into())
+    );
+    assert_eq!(
+        app.logs().last().map(|l| l.level),
+        Some(app::LogLevel::Info)
+    );
+}
+
+fn selected_names(app: &app::App) -> Vec<String> {
+    let mut names = app
+        .selection()
+        .iter()
+        .map(|n| n.relative_path.clone())
+        .collect::<Vec<String>>();
+    names.sort();
+    names
+}
+
+#[test]
+fn test_select_range_from_last_focus() {
+    let dir = create_dir("select_range", &["a", "b", "c", "d", "e"]);
+    let app = change_directory(app::App::new(config::Config::default(), dir.clone()), &dir);
+
+    // Forward
+    let app = handle(app, app::ExternalMsg::FocusByIndex(1));
+    let app = handle(app, app::ExternalMsg::Select);
+    let app = handle(app, app::ExternalMsg::FocusByIndex(3));
+    let app = handle(app, app::ExternalMsg::SelectRangeFromLastFocus);
+    assert_eq!(selected_names(&app), vec!["b", "c", "d"]);
+
+    // Backward
+    let app = handle(app, app::ExternalMsg::ClearSelection);
+    let app = handle(app, app::ExternalMsg::FocusByIndex(4));
+    let app = handle(app, app::ExternalMsg::Select);
+    let app = handle(app, app::ExternalMsg::FocusByIndex(2));
+    let app = handle(app, app::ExternalMsg::SelectRangeFromLastFocus);
+    assert_eq!(selected_names(&app), vec!["c", "d", "e"]);
+}
+
+#[test]
+fn test_select_range_from_last_focus_without_anchor() {
+    let dir = create_dir("select_range_no_anchor", &["a", "b", "c"]);
+    let app = change_directory(app::App::new(config::Config::default(), dir.clone()), &dir);
+
+    let app = handle(app, app::ExternalMsg::FocusByIndex(1));
+    let app = handle(app, app::ExternalMsg::SelectRangeFromLastFocus);
+    assert_eq!(selected_names(&app), vec!["b"]);
+}
+
+#[test]
+fn test_preview_filter_impact() {
+    let dir = create_dir("preview_filter", &[".hidden", "a.md", "b.md", "c.rs"]);
+    let app = change_directory(app::App::new(config::Config::default(), dir.clone()), &dir);
+
+    let filter = app::NodeFilterApplicable::new(
+        app::NodeFilter::RelativePathDoesEndWith,
+        ".MD".into(),
+        false,
+    );
+    assert_eq!(app.preview_filter_impact(&filter), (2, 4));
+
+    let explorer_config = app.explorer_config().clone();
+    let app = handle(app, app::ExternalMsg::SetInputBuffer(".rs".into()));
+    let app = handle(
+        app,
+        app::ExternalMsg::PreviewFilterFromInput(
+            serde_yaml::from_str("{filter: RelativePathDoesEndWith}").unwrap(),
+        ),
+    );
+    assert_eq!(
+        app.logs().last().map(|l| l.message.clone()),
+        Some("filter would hide 3 of 4 nodes".into())
+    );
+    assert_eq!(app.explorer_config(), &explorer_config);
+}
+
+#[test]
+fn test_focus_first_selection_across_dirs() {
+    let dir = create_dir("selection_across_dirs", &["a", "b"]);
+    let other = create_dir("selection_across_dirs_other", &["x", "y", "z"]);
+    let gone = create_dir("selection_across_dirs_gone", &["g"]);
+
+    let app = change_directory(
+        app::App::new(config::Config::default(), gone.clone()),
+        &gone,
+    );
+    let app = handle(app, app::ExternalMsg::Select);
+    fs::remove_dir_all(&gone).unwrap();
+
+    let app = change_directory(app, &other);
+    let app = handle(app, app::ExternalMsg::FocusByFileName("y".into()));
+    let app = handle(app, app::ExternalMsg::Select);
+    let app = handle(app, app::ExternalMsg::FocusFirst);
+
+    let app = change_directory(app, &dir);
+    let app = handle(app, app::ExternalMsg::Select);
+
+    let app = handle(app, app::ExternalMsg::FocusFirstSelectionAcrossDirs);
+    assert_eq!(app.pwd(), &other);
+    assert_eq!(
+        app.focused_node().map(|n| n.relative_path.clone()),
+        Some("y".into())
+    );
+    assert!(app.logs().iter().any(
+        |l| l.level == app::LogLevel::Error && l.message.contains("selection_across_dirs_gone")
+    ));
+}
+
+#[test]
+fn test_focus_first_selection_across_dirs_explores_unbuffered() {
+    let dir = create_dir("selection_across_dirs_unbuffered", &["a"]);
+    let other = create_dir("selection_across_dirs_unbuffered_other", &["x", "y"]);
+
+    let app = change_directory(
+        app::App::new(config::Config::default(), other.clone()),
+        &other,
+    );
+    let app = handle(app, app::ExternalMsg::FocusByFileName("y".into()));
+    let app = handle(app, app::ExternalMsg::Select);
+
+    // Forget about all the buffers.
+    let app = change_directory(app, &dir);
+    let app = handle(app, app::ExternalMsg::HardRefresh);
+    let app = handle_internal(
+        app,
+        app::InternalMsg::AddDirectory(dir.clone(), directory_buffer(&dir, 0)),
+    );
+    let mut app = handle(app, app::ExternalMsg::FocusFirstSelectionAcrossDirs);
+    assert_eq!(app.pwd(), &other);
+    assert_eq!(app.explore_focus(), Some("y".into()));
+
+    let mut explored = false;
+    while let Some(msg) = app.pop_msg_out() {
+        explored |= msg == app::MsgOut::Explore;
+    }
+    assert!(explored);
+
+    let buf = explorer::explore_sync(
+        app.explorer_config().clone(),
+        other.clone(),
+        app.explore_focus(),
+    )
+    .unwrap();
+    let app = handle_internal(app, app::InternalMsg::AddDirectory(other.clone(), buf));
+    assert_eq!(app.focused_node().unwrap().relative_path, "y");
+}
+
+#[test]
+fn test_focus_by_page_uses_layout() {
+    let dir = create_dir("focus_by_page", &["a", "b", "c", "d", "e", "f", "g"]);
+    let app = change_directory(app::App::new(config::Config::default(), dir.clone()), &dir);
+
+    let app = handle_internal(
+        app,
+        app::InternalMsg::SetLayout {
+            rows: 20,
+            cols: 80,
+            list_height: 3,
+        },
+    );
+    assert_eq!(app.layout().list_height, 3);
+
+    let app = handle(app, app::ExternalMsg::FocusNextByPage);
+    assert_eq!(app.directory_buffer().map(|d| d.focus), Some(3));
+
+    let app = handle(app, app::ExternalMsg::FocusNextByPage);
+    assert_eq!(app.directory_buffer().map(|d| d.focus), Some(6));
+
+    let app = handle(app, app::ExternalMsg::FocusNextByPage);
+    assert_eq!(app.directory_buffer().map(|d| d.focus), Some(6));
+
+    let app = handle(app, app::ExternalMsg::FocusPreviousByPage);
+    assert_eq!(app.directory_buffer().map(|d| d.focus), Some(3));
+}
+
+#[test]
+fn test_clear_screen_and_refresh() {
+    let mut app = handle(
+        app::App::new(
+            config::Config::default(),
+            std::env::temp_dir().to_string_lossy().into(),
+        ),
+        app::ExternalMsg::ClearScreenAndRefresh,
+    );
+    assert_eq!(app.pop_msg_out(), Some(app::MsgOut::ClearScreen));
+    assert_eq!(app.pop_msg_out(), Some(app::MsgOut::Refresh));
+    assert_eq!(app.pop_msg_out(), None);
+}
+
+#[test]
+fn test_enter_file() {
+    let dir = create_dir("enter_file", &["a/", "b"]);
+
+    let app = change_directory(app::App::new(config::Config::default(), dir.clone()), &dir);
+    let mut app = handle(app, app::ExternalMsg::FocusByFileName("b".into()));
+    while app.pop_msg_out().is_some() {}
+    let mut app = handle(app, app::ExternalMsg::Enter);
+    assert_eq!(app.pwd(), &dir);
+    assert_eq!(app.pop_msg_out(), None);
+
+    let mut config = config::Config::default();
+    config.general.enter_file_focuses_parent = true;
+    let app = change_directory(app::App::new(config, dir.clone()), &dir);
+    let mut app = handle(app, app::ExternalMsg::FocusByFileName("b".into()));
+    while app.pop_msg_out().is_some() {}
+    let mut app = handle(app, app::ExternalMsg::Enter);
+    assert_eq!(app.pwd(), &dir);
+    assert_eq!(app.pop_msg_out(), Some(app::MsgOut::Refresh));
+    assert_eq!(
+        app.focused_node().map(|n| n.relative_path.clone()),
+        Some("b".into())
+    );
+}
+
+#[test]
+fn test_enter_file_focuses_parent_from_recursive_listing() {
+    let dir = create_dir("enter_file_recursive", &["a", "sub/"]);
+    fs::File::create(format!("{}/sub/x", dir)).unwrap();
+    let sub = format!("{}/sub", dir);
+
+    let mut config = config::Config::default();
+    config.general.enter_file_focuses_parent = true;
+    let app = change_directory(app::App::new(config, dir.clone()), &dir);
+    let flat =
+        explorer::explore_recursive_sync(app.explorer_config().clone(), dir.clone(), None).unwrap();
+    let app = handle_internal(app, app::InternalMsg::AddDirectory(dir.clone(), flat));
+    let app = handle(app, app::ExternalMsg::FocusByFileName("sub/x".into()));
+    assert_eq!(app.focused_node().unwrap().relative_path, "sub/x");
+
+    let app = handle(app, app::ExternalMsg::Enter);
+    assert_eq!(app.pwd(), &sub);
+    assert_eq!(app.explore_focus(), Some("x".into()));
+
+    let buf = explorer::explore_sync(
+        app.explorer_config().clone(),
+        sub.clone(),
+        app.explore_focus(),
+    )
+    .unwrap();
+    let app = handle_internal(app, app::InternalMsg::AddDirectory(sub.clone(), buf));
+    assert_eq!(app.focused_node().unwrap().relative_path, "x");
+}
+
+#[test]
+fn test_explore_and_focus() {
+    let dir = create_dir("explore_and_focus", &["a", "b", "c"]);
+
+    let mut app = handle(
+        app::App::new(config::Config::default(), dir.clone()),
+        app::ExternalMsg::ExploreAndFocus(format!("{}/c", dir)),
+    );
+    assert_eq!(app.pwd(), &dir);
+
+    let mut msgs = vec![];
+    while let Some(msg) = app.pop_msg_out() {
+        msgs.push(msg);
+    }
+    assert_eq!(msgs.last(), Some(&app::MsgOut::Explore));
+
+    let app = handle_internal(
+        app,
+        app::InternalMsg::AddDirectory(dir.clone(), directory_buffer(&dir, 0)),
+    );
+    assert_eq!(
+        app.focused_node().map(|n| n.relative_path.clone()),
+        Some("c".into())
+    );
+}
+
+#[test]
+fn test_hard_refresh() {
+    let dir = create_dir("hard_refresh", &["a", "b"]);
+    let app = change_directory(app::App::new(config::Config::default(), dir.clone()), &dir);
+    let app = handle(app, app::ExternalMsg::Select);
+    let app = handle(app, app::ExternalMsg::FocusNext);
+    let app = handle(app, app::ExternalMsg::Select);
+    fs::remove_file(format!("{}/a", dir)).unwrap();
+
+    let mut app = handle(app, app::ExternalMsg::HardRefresh);
+    assert!(app.directory_buffers().is_empty());
+    assert_eq!(selected_names(&app), vec!["b"]);
+    assert_eq!(
+        app.logs().last().map(|l| l.message.clone()),
+        Some("cleared 1 directory buffers and 1 vanished selections".into())
+    );
+
+    let mut last = None;
+    while let Some(msg) = app.pop_msg_out() {
+        last = Some(msg);
+    }
+    assert_eq!(last, Some(app::MsgOut::Explore));
+}
+
+#[test]
+fn test_focus_by_index_or_relative_from_input() {
+    let dir = create_dir(
+        "focus_by_index_or_relative",
+        &["a", "b", "c", "d", "e", "f"],
+    );
+    let app = change_directory(app::App::new(config::Config::default(), dir.clone()), &dir);
+
+    let focus_with = |app: app::App, input: &str| {
+        let app = handle(app, app::ExternalMsg::SetInputBuffer(input.into()));
+        handle(app, app::ExternalMsg::FocusByIndexOrRelativeFromInput)
+    };
+
+    let app = focus_with(app, "5");
+    assert_eq!(app.directory_buffer().map(|d| d.focus), Some(5));
+
+    let app = focus_with(app, "-2");
+    assert_eq!(app.directory_buffer().map(|d| d.focus), Some(3));
+
+    let app = focus_with(app, "+2");
+    assert_eq!(app.directory_buffer().map(|d| d.focus), Some(5));
+
+    let app = focus_with(app, "x");
+    assert_eq!(app.directory_buffer().map(|d| d.focus), Some(5));
+    assert_eq!(
+        app.logs().last().map(|l| l.level),
+        Some(app::LogLevel::Error)
+    );
+}
+
+#[test]
+fn test_toggle_last_directory() {
+    let a = create_dir("toggle_last_directory_a", &[]);
+    let b = create_dir("toggle_last_directory_b", &[]);
+
+    let app = handle(
+        app::App::new(config::Config::default(), a.clone()),
+        app::ExternalMsg::ChangeDirectory(a.clone()),
+    );
+    let app = handle(app, app::ExternalMsg::ChangeDirectory(b.clone()));
+    assert_eq!(app.pwd(), &b);
+
+    let app = handle(app, app::ExternalMsg::ToggleLastDirectory);
+    assert_eq!(app.pwd(), &a);
+
+    let app = handle(app, app::ExternalMsg::ToggleLastDirectory);
+    assert_eq!(app.pwd(), &b);
+}
+
+#[test]
+fn test_write_selection_to_temp_file_and_call() {
+    let cmd: app::Command =
+        serde_yaml::from_str("{command: xargs, args: [-a, '{selection_file}', ls]}").unwrap();
+
+    let mut app = handle(
+        app::App::new(
+            config::Config::default(),
+            std::env::temp_dir().to_string_lossy().into(),
+        ),
+        app::ExternalMsg::WriteSelectionToTempFileAndCall(cmd.clone()),
+    );
+    assert_eq!(
+        app.pop_msg_out(),
+        Some(app::MsgOut::CallWithSelectionFile {
+            command: cmd.clone(),
+            nul_separated: false
+        })
+    );
+
+    let mut app = handle(
+        app,
+        app::ExternalMsg::WriteNulSeparatedSelectionToTempFileAndCall(cmd.clone()),
+    );
+    assert_eq!(
+        app.pop_msg_out(),
+        Some(app::MsgOut::CallWithSelectionFile {
+            command: cmd.clone(),
+            nul_separated: true
+        })
+    );
+
+    let cmd = cmd.with_selection_file("/tmp/selection");
+    assert_eq!(cmd.command, "xargs");
+    assert_eq!(cmd.args, vec!["-a", "/tmp/selection", "ls"]);
+}
+
+#[test]
+fn test_result_format() {
+    let dir = create_dir("result_format", &["a.txt", "b/"]);
+    fs::write(format!("{}/a.txt", dir), "hello").unwrap();
+
+    let app = change_directory(app::App::new(config::Config::default(), dir.clone()), &dir);
+    assert_eq!(app.result_str(), format!("{}/a.txt", dir));
+
+    let mut config = config::Config::default();
+    config.general.result_format = Some("{name}\t{size}\t{mime}\t{is_dir}".into());
+    let app = change_directory(app::App::new(config, dir.clone()), &dir);
+    assert_eq!(app.result_str(), "a.txt\t5\ttext/plain\tfalse");
+
+    let mut config = config::Config::default();
+    config.general.result_format = Some("{path} {owner}".into());
+    let app = change_directory(app::App::new(config, dir.clone()), &dir);
+    let app = handle(app, app::ExternalMsg::FocusNext);
+    let app = handle(app, app::ExternalMsg::Select);
+    assert_eq!(app.result_str(), format!("{}/b {{owner}}", dir));
+}
+
+#[test]
+fn test_clear_logs_on_enter() {
+    let mut config = config::Config::default();
+    if let Some(mode) = config.modes.get_mut("action") {
+        mode.clear_logs_on_enter = true;
+    };
+
+    let app = app::App::new(config, ".".into());
+    let app = handle(app, app::ExternalMsg::LogInfo("noise".into()));
+
+    let app = handle(app, app::ExternalMsg::SwitchMode("search".into()));
+    assert_eq!(app.logs().len(), 1);
+
+    let app = handle(app, app::ExternalMsg::SwitchMode("action".into()));
+    assert!(app.logs().is_empty());
+}
+
+#[test]
+fn test_terminate_error() {
+    let err = app::App::new(
+        config::Config::default(),
+        std::env::temp_dir().to_string_lossy().into(),
+    )
+    .enqueue(app::Task::new(
+        0,
+        app::MsgIn::External(app::ExternalMsg::Terminate),
+        None,
+    ))
+    .possibly_mutate()
+    .err()
+    .unwrap();
+
+    assert_eq!(
+        err.downcast_ref::<app::AppError>(),
+        Some(&app::AppError::Terminated)
+    );
+}
+
+#[test]
+fn test_peek_msg_out() {
+    let mut app = handle(
+        app::App::new(
+            config::Config::default(),
+            std::env::temp_dir().to_string_lossy().into(),
+        ),
+        app::ExternalMsg::ClearScreenAndRefresh,
+    );
+    assert_eq!(app.msg_out_len(), 2);
+
+    assert_eq!(app.peek_msg_out(), Some(&app::MsgOut::ClearScreen));
+    assert_eq!(app.msg_out_len(), 2);
+    assert_eq!(app.pop_msg_out(), Some(app::MsgOut::ClearScreen));
+
+    assert_eq!(app.peek_msg_out(), Some(&app::MsgOut::Refresh));
+    assert_eq!(app.pop_msg_out(), Some(app::MsgOut::Refresh));
+
+    assert_eq!(app.peek_msg_out(), None);
+    assert_eq!(app.msg_out_len(), 0);
+}
+
+#[test]
+fn test_max_selection() {
+    let dir = create_dir("max_selection", &["a", "b", "c"]);
+    let mut config = config::Config::default();
+    config.general.max_selection = Some(2);
+
+    let app = change_directory(app::App::new(config, dir.clone()), &dir);
+    let app = handle(app, app::ExternalMsg::ToggleSelection);
+    let app = handle(app, app::ExternalMsg::FocusNext);
+    let app = handle(app, app::ExternalMsg::ToggleSelection);
+    let app = handle(app, app::ExternalMsg::FocusNext);
+
+    let app = handle(app, app::ExternalMsg::ToggleSelection);
+    assert_eq!(selected_names(&app), vec!["a", "b"]);
+    assert_eq!(
+        app.logs().last().map(|l| l.level),
+        Some(app::LogLevel::Error)
+    );
+
+    let app = handle(app, app::ExternalMsg::FocusPrevious);
+    let app = handle(app, app::ExternalMsg::ToggleSelection);
+    assert_eq!(selected_names(&app), vec!["a"]);
+
+    let app = handle(app, app::ExternalMsg::FocusNext);
+    let app = handle(app, app::ExternalMsg::Select);
+    assert_eq!(selected_names(&app), vec!["a", "c"]);
+}
+
+#[test]
+fn test_focus_random() {
+    let dir = create_dir("focus_random", &["a", "b", "c", "d", "e", "f", "g", "h"]);
+    let empty = create_dir("focus_random_empty", &[]);
+
+    let focuses = |seed: u64| {
+        let mut app = change_directory(
+            app::App::new(config::Config::default(), dir.clone()).with_random_seed(seed),
+            &dir,
+        );
+        let mut focuses = vec![];
+        for _ in 0..10 {
+            app = handle(app, app::ExternalMsg::FocusRandom);
+            focuses.push(app.directory_buffer().map(|d| d.focus).unwrap());
+        }
+        focuses
+    };
+
+    assert_eq!(focuses(42), focuses(42));
+    assert!(focuses(42).iter().all(|f| *f < 8));
+    assert!(focuses(42).iter().any(|f| *f != focuses(42)[0]));
+
+    let app = change_directory(
+        app::App::new(config::Config::default(), empty.clone()).with_random_seed(42),
+        &empty,
+    );
+    let app = handle(app, app::ExternalMsg::FocusRandom);
+    assert_eq!(app.directory_buffer().map(|d| d.focus), Some(0));
+}
+
+#[test]
+fn test_toggle_selection_by_mime() {
+    let dir = create_dir(
+        "toggle_selection_by_mime",
+        &["a.png", "b.jpg", "c.txt", "d.md"],
+    );
+    let app = change_directory(app::App::new(config::Config::default(), dir.clone()), &dir);
+    let app = handle(app, app::ExternalMsg::FocusByFileName("a.png".into()));
+    let app = handle(app, app::ExternalMsg::Select);
+    let app = handle(app, app::ExternalMsg::FocusByFileName("c.txt".into()));
+    let app = handle(app, app::ExternalMsg::Select);
+
+    let app = handle(
+        app,
+        app::ExternalMsg::ToggleSelectionByMime("image/".into()),
+    );
+    assert_eq!(selected_names(&app), vec!["b.jpg", "c.txt"]);
+    assert_eq!(
+        app.logs().last().map(|l| l.message.clone()),
+        Some("selected 1 and unselected 1 nodes".into())
+    );
+
+    let app = handle(
+        app,
+        app::ExternalMsg::ToggleSelectionByMime("image/".into()),
+    );
+    assert_eq!(selected_names(&app), vec!["a.png", "c.txt"]);
+}
+
+#[test]
+fn test_focus_by_node_type() {
+    let dir = create_dir("focus_by_node_type", &["a/", "b", "c", "d/", "e"]);
+    let app = change_directory(app::App::new(config::Config::default(), dir.clone()), &dir);
+    let focused = |app: &app::App| app.focused_node().map(|n| n.relative_path.clone()).unwrap();
+
+    let app = handle(app, app::ExternalMsg::FocusNextDirectory);
+    assert_eq!(focused(&app), "d");
+    let app = handle(app, app::ExternalMsg::FocusNextDirectory);
+    assert_eq!(focused(&app), "a");
+    let app = handle(app, app::ExternalMsg::FocusPreviousDirectory);
+    assert_eq!(focused(&app), "d");
+
+    let app = handle(app, app::ExternalMsg::FocusNextFile);
+    assert_eq!(focused(&app), "e");
+    let app = handle(app, app::ExternalMsg::FocusNextFile);
+    assert_eq!(focused(&app), "b");
+    let app = handle(app, app::ExternalMsg::FocusPreviousFile);
+    assert_eq!(focused(&app), "e");
+
+    let files = create_dir("focus_by_node_type_files", &["a", "b"]);
+    let app = change_directory(app, &files);
+    let app = handle(app, app::ExternalMsg::FocusNextDirectory);
+    assert_eq!(focused(&app), "a");
+    assert_eq!(
+        app.logs().last().map(|l| l.message.clone()),
+        Some("no directory found".into())
+    );
+}
+
+#[test]
+fn test_clear_input_buffer() {
+    let app = app::App::new(
+        config::Config::default(),
+        std::env::temp_dir().to_string_lossy().into(),
+    );
+    let app = handle(app, app::ExternalMsg::SetInputBuffer("foo".into()));
+    assert_eq!(app.input_buffer(), Some("foo".into()));
+
+    let app = handle(app, app::ExternalMsg::ClearInputBuffer);
+    assert_eq!(app.input_buffer(), Some("".into()));
+
+    let app = handle(app, app::ExternalMsg::ResetInputBuffer);
+    assert_eq!(app.input_buffer(), None);
+}
+
+#[test]
+fn test_batch_refreshes_once() {
+    let dir = create_dir("batch_refreshes_once", &["a", "b", "c", "d"]);
+    let mut app = change_directory(app::App::new(config::Config::default(), dir.clone()), &dir);
+    while app.pop_msg_out().is_some() {}
+
+    let mut app = handle(app, app::ExternalMsg::BeginBatch);
+    assert!(app.suppress_refresh());
+    for _ in 0..3 {
+        app = handle(app, app::ExternalMsg::FocusNext);
+    }
+    assert_eq!(app.msg_out_len(), 0);
+
+    let mut app = handle(app, app::ExternalMsg::EndBatch);
+    assert!(!app.suppress_refresh());
+    assert_eq!(app.focused_node().unwrap().relative_path, "d");
+
+    let mut refreshes = 0;
+    while let Some(msg) = app.pop_msg_out() {
+        if msg == app::MsgOut::Refresh {
+            refreshes += 1;
+        }
+    }
+    assert_eq!(refreshes, 1);
+}
+
+#[test]
+fn test_focus_next_by_full_extension() {
+    let dir = create_dir(
+        "focus_next_by_full_extension",
+        &["a.gz", "b.tar.gz", "c.tgz", "d.TAR.GZ"],
+    );
+    let app = change_directory(app::App::new(config::Config::default(), dir.clone()), &dir);
+    let focused = |app: &app::App| app.focused_node().map(|n| n.relative_path.clone()).unwrap();
+
+    let app = handle(
+        app,
+        app::ExternalMsg::FocusNextByFullExtension("tar.gz".into()),
+    );
+    assert_eq!(focused(&app), "b.tar.gz");
+    let app = handle(
+        app,
+        app::ExternalMsg::FocusNextByFullExtension("tar.gz".into()),
+    );
+    assert_eq!(focused(&app), "d.TAR.GZ");
+    let app = handle(app, app::ExternalMsg::FocusNextByFullExtension("gz".into()));
+    assert_eq!(focused(&app), "a.gz");
+    let app = handle(
+        app,
+        app::ExternalMsg::FocusNextByFullExtension("zip".into()),
+    );
+    assert_eq!(focused(&app), "a.gz");
+}
+
+#[test]
+fn test_sleep_is_capped() {
+    let mut app = app::App::new(
+        config::Config::default(),
+        std::env::temp_dir().to_string_lossy().into(),
+    );
+    while app.pop_msg_out().is_some() {}
+
+    let mut app = handle(app, app::ExternalMsg::Sleep(500));
+    assert_eq!(
+        app.pop_msg_out(),
+        Some(app::MsgOut::Sleep(Duration::from_millis(500)))
+    );
+
+    let mut app = handle(app, app::ExternalMsg::Sleep(app::MAX_SLEEP_MILLIS * 10));
+    assert_eq!(
+        app.pop_msg_out(),
+        Some(app::MsgOut::Sleep(Duration::from_millis(
+            app::MAX_SLEEP_MILLIS
+        )))
+    );
+    assert_eq!(app.logs().last().unwrap().level, app::LogLevel::Warning);
+}
+
+#[test]
+fn test_print_focused_metadata_and_quit() {
+    let dir = create_dir("print_focused_metadata", &["a"]);
+    fs::write(PathBuf::from(&dir).join("a"), "hello").unwrap();
+    let mut app = change_directory(app::App::new(config::Config::default(), dir.clone()), &dir);
+    while app.pop_msg_out().is_some() {}
+
+    let mut app = handle(app, app::ExternalMsg::PrintFocusedMetadataAndQuit);
+    assert_eq!(
+        app.pop_msg_out(),
+        Some(app::MsgOut::PrintFocusedMetadataAndQuit)
+    );
+
+    let json: serde_json::Value =
+        serde_json::from_str(&app.focused_metadata_str().unwrap()).unwrap();
+    assert_eq!(json["absolute_path"], format!("{}/a", dir));
+    assert_eq!(json["size"], 5);
+
+    let empty = create_dir("print_focused_metadata_empty", &[]);
+    let mut app = change_directory(app, &empty);
+    while app.pop_msg_out().is_some() {}
+    let mut app = handle(app, app::ExternalMsg::PrintFocusedMetadataAndQuit);
+    assert_eq!(app.pop_msg_out(), None);
+    assert_eq!(
+        app.logs().last().map(|l| l.message.clone()),
+        Some("no node is focused".into())
+    );
+}
+
+#[test]
+fn test_toggle_selection_and_focus_next() {
+    let dir = create_dir("toggle_selection_and_focus_next", &["a", "b", "c"]);
+    let mut app = change_directory(app::App::new(config::Config::default(), dir.clone()), &dir);
+    while app.pop_msg_out().is_some() {}
+
+    let mut app = handle(app, app::ExternalMsg::ToggleSelectionAndFocusNext);
+    assert_eq!(selected_names(&app), vec!["a".to_string()]);
+    assert_eq!(app.focused_node().unwrap().relative_path, "b");
+    assert_eq!(app.pop_msg_out(), Some(app::MsgOut::Refresh));
+    assert_eq!(app.pop_msg_out(), None);
+
+    let app = handle(app, app::ExternalMsg::SelectAndFocusNext);
+    assert_eq!(selected_names(&app), vec!["a".to_string(), "b".to_string()]);
+    assert_eq!(app.focused_node().unwrap().relative_path, "c");
+
+    let app = handle(app, app::ExternalMsg::FocusFirst);
+    let app = handle(app, app::ExternalMsg::UnSelectAndFocusNext);
+    assert_eq!(selected_names(&app), vec!["b".to_string()]);
+    assert_eq!(app.focused_node().unwrap().relative_path, "b");
+}
+
+#[test]
+fn test_start_mode() {
+    let mut config = config::Config::default();
+    config.general.start_mode = Some("go to".into());
+    let app = app::App::new(config, "/".into());
+    assert_eq!(app.mode().name, "go to");
+    assert!(app.logs().is_empty());
+
+    let mut config = config::Config::default();
+    config.general.start_mode = Some("missing".into());
+    let app = app::App::new(config, "/".into());
+    assert_eq!(app.mode().name, "default");
+    assert_eq!(app.logs().last().unwrap().level, app::LogLevel::Error);
+}
+
+#[test]
+fn test_set_selection() {
+    let dir = create_dir("set_selection", &["a", "b", "c/"]);
+    let app = change_directory(app::App::new(config::Config::default(), dir.clone()), &dir);
+    let app = handle(app, app::ExternalMsg::Select);
+    assert_eq!(selected_names(&app), vec!["a".to_string()]);
+
+    let app = handle(
+        app,
+        app::ExternalMsg::SetSelection(vec![
+            format!("{}/b", dir),
+            format!("{}/missing", dir),
+            format!("{}/c", dir),
+        ]),
+    );
+    assert_eq!(selected_names(&app), vec!["b".to_string(), "c".to_string()]);
+    assert_eq!(app.logs().len(), 1);
+    assert_eq!(app.logs()[0].level, app::LogLevel::Warning);
+}
+
+#[test]
+fn test_focus_follows_sort() {
+    let dir = create_dir("focus_follows_sort", &["a", "b", "c", "d"]);
+    let reverse = app::NodeSorterApplicable::new(app::NodeSorter::ByRelativePath, true);
+
+    let app = change_directory(app::App::new(config::Config::default(), dir.clone()), &dir);
+    let app = handle(app, app::ExternalMsg::FocusNext);
+    let app = handle(app, app::ExternalMsg::SortNodes(reverse.clone()));
+    let names = app
+        .directory_buffer()
+        .unwrap()
+        .nodes
+        .iter()
+        .map(|n| n.relative_path.clone())
+        .collect::<Vec<String>>();
+    assert_eq!(names, vec!["d", "c", "b", "a"]);
+    assert_eq!(app.focused_node().unwrap().relative_path, "b");
+
+    let mut config = config::Config::default();
+    config.general.focus_follows_sort = false;
+    let app = change_directory(app::App::new(config, dir.clone()), &dir);
+    let app = handle(app, app::ExternalMsg::FocusNext);
+    let app = handle(app, app::ExternalMsg::SortNodes(reverse));
+    assert_eq!(app.focused_node().unwrap().relative_path, "c");
+}
+
+#[test]
+fn test_print_pwd_and_quit() {
+    let dir = create_dir("print_pwd_and_quit", &["a", "b/"]);
+    let mut app = change_directory(app::App::new(config::Config::default(), dir.clone()), &dir);
+    while app.pop_msg_out().is_some() {}
+    assert!(app.focused_node().unwrap().is_file);
+
+    let mut app = handle(app, app::ExternalMsg::PrintPwdAndQuit);
+    assert_eq!(app.pop_msg_out(), Some(app::MsgOut::PrintPwdAndQuit));
+    assert_eq!(app.pwd(), &dir);
+}
+
+#[test]
+fn test_focus_next_at_depth() {
+    let dir = create_dir("focus_next_at_depth", &["a/", "a/b/", "a/b/c", "a/d", "e"]);
+    let nodes = ["a", "a/b", "a/b/c", "a/d", "e"]
+        .iter()
+        .map(|p| app::Node::new(dir.clone(), p.to_string()))
+        .collect::<Vec<app::Node>>();
+    let buffer = app::DirectoryBuffer::new(dir.clone(), nodes.clone(), nodes, 0);
+
+    let app = handle(
+        app::App::new(config::Config::default(), dir.clone()),
+        app::ExternalMsg::ChangeDirectory(dir.clone()),
+    );
+    let app = handle_internal(app, app::InternalMsg::AddDirectory(dir, buffer));
+    let focused = |app: &app::App| app.focused_node().map(|n| n.relative_path.clone()).unwrap();
+
+    let app = handle(app, app::ExternalMsg::FocusNextAtDepth(2));
+    assert_eq!(focused(&app), "a/b");
+    let app = handle(app, app::ExternalMsg::FocusNextAtDepth(2));
+    assert_eq!(focused(&app), "a/d");
+    let app = handle(app, app::ExternalMsg::FocusNextAtDepth(3));
+    assert_eq!(focused(&app), "a/b/c");
+    let app = handle(app, app::ExternalMsg::FocusNextAtDepth(1));
+    assert_eq!(focused(&app), "e");
+    let app = handle(app, app::ExternalMsg::FocusNextAtDepth(4));
+    assert_eq!(focused(&app), "e");
+}
+
+#[test]
+fn test_back_exits_recursive_exploration() {
+    let dir = create_dir("back_exits_recursive", &["a/", "a/b"]);
+    let mut app = change_directory(app::App::new(config::Config::default(), dir.clone()), &dir);
+    while app.pop_msg_out().is_some() {}
+
+    let mut app = handle(
+        app,
+        app::ExternalMsg::ExploreRecursive { max_depth: Some(2) },
+    );
+    assert!(app.exploring_recursively());
+    assert_eq!(
+        app.pop_msg_out(),
+        Some(app::MsgOut::ExploreRecursive(Some(2)))
+    );
+
+    let mut app = handle(app, app::ExternalMsg::Back);
+    assert!(!app.exploring_recursively());
+    assert_eq!(app.pwd(), &dir);
+    assert_eq!(app.pop_msg_out(), Some(app::MsgOut::Explore));
+}
+
+#[test]
+fn test_explore_recursive_rejects_zero_depth() {
+    let dir = create_dir("explore_recursive_zero_depth", &["a/", "a/b"]);
+    let mut app = change_directory(app::App::new(config::Config::default(), dir.clone()), &dir);
+    while app.pop_msg_out().is_some() {}
+
+    let mut app = handle(
+        app,
+        app::ExternalMsg::ExploreRecursive { max_depth: Some(0) },
+    );
+    assert!(!app.exploring_recursively());
+    assert_eq!(app.pop_msg_out(), None);
+    assert_eq!(
+        app.logs().last().map(|l| l.message.clone()),
+        Some("max_depth must be at least 1".into())
+    );
+}
+
+#[test]
+fn test_selection_diff() {
+    let previous = vec!["/a".to_string(), "/b".to_string(), "/c".to_string()];
+    let current = vec!["/d".to_string(), "/b".to_string(), "/a".to_string()];
+
+    let (added, removed) = app::selection_diff(&previous, &current);
+    assert_eq!(added, vec!["/d".to_string()]);
+    assert_eq!(removed, vec!["/c".to_string()]);
+
+    let (added, removed) = app::selection_diff(&[], &previous);
+    assert_eq!(added, previous);
+    assert!(removed.is_empty());
+}
+
+#[test]
+fn test_toggle_filter_by_focused_extension() {
+    let dir = create_dir("toggle_filter_by_focused_extension", &["a.o", "b.c", "c"]);
+    let app = change_directory(app::App::new(config::Config::default(), dir.clone()), &dir);
+    let filters = app.explorer_config().filters().clone();
+
+    let app = handle(app, app::ExternalMsg::ToggleFilterByFocusedExtension);
+    assert_eq!(app.explorer_config().filters().len(), filters.len() + 1);
+    let node = app::Node::new(dir.clone(), "x.o".into());
+    assert!(!app.explorer_config().apply(&node));
+
+    let app = handle(app, app::ExternalMsg::ToggleFilterByFocusedExtension);
+    assert_eq!(app.explorer_config().filters(), &filters);
+
+    let app = handle(app, app::ExternalMsg::FocusLast);
+    let app = handle(app, app::ExternalMsg::ToggleFilterByFocusedExtension);
+    assert_eq!(app.explorer_config().filters(), &filters);
+    assert_eq!(app.logs().last().unwrap().level, app::LogLevel::Info);
+}
+
+#[test]
+fn test_auto_prune_selection() {
+    let dir = create_dir("auto_prune_selection", &["a", "b"]);
+    let mut config = config::Config::default();
+    config.general.auto_prune_selection = true;
+
+    let app = change_directory(app::App::new(config, dir.clone()), &dir);
+    let app = handle(app, app::ExternalMsg::Select);
+    let app = handle(app, app::ExternalMsg::FocusNext);
+    let app = handle(app, app::ExternalMsg::Select);
+    assert_eq!(app.result().len(), 2);
+
+    fs::remove_file(PathBuf::from(&dir).join("a")).unwrap();
+    let result = app
+        .result()
+        .iter()
+        .map(|n| n.relative_path.clone())
+        .collect::<Vec<String>>();
+    assert_eq!(result, vec!["b".to_string()]);
+    assert_eq!(app.selection().len(), 2);
+
+    // What the commands get as `XPLR_SELECTION`.
+    let pruned = app
+        .pruned_selection()
+        .iter()
+        .map(|n| n.relative_path.clone())
+        .collect::<Vec<String>>();
+    assert_eq!(pruned, vec!["b".to_string()]);
+}
+
+#[test]
+fn test_focus_by_absolute_path() {
+    let dir = create_dir("focus_by_absolute_path", &["a", "b/", "b/c", "b/d"]);
+    let sub = format!("{}/b", dir);
+    let mut app = change_directory(app::App::new(config::Config::default(), dir.clone()), &dir);
+    while app.pop_msg_out().is_some() {}
+
+    let mut app = handle(
+        app,
+        app::ExternalMsg::FocusByAbsolutePath(format!("{}/../b/d", sub)),
+    );
+    assert_eq!(app.pwd(), &sub);
+    assert!(app.directory_buffer().is_none());
+    assert!(std::iter::from_fn(|| app.pop_msg_out()).any(|m| m == app::MsgOut::Explore));
+
+    let app = handle_internal(
+        app,
+        app::InternalMsg::AddDirectory(sub.clone(), directory_buffer(&sub, 0)),
+    );
+    assert_eq!(app.focused_node().unwrap().relative_path, "d");
+
+    let app = handle(
+        app,
+        app::ExternalMsg::FocusByAbsolutePath(format!("{}/missing", dir)),
+    );
+    assert_eq!(app.pwd(), &sub);
+    assert_eq!(app.logs().last().unwrap().level, app::LogLevel::Error);
+}
+
+#[test]
+fn test_symlink_resolution() {
+    let dir = create_dir("symlink_resolution", &["real/", "real/a", "other/"]);
+    std::os::unix::fs::symlink(format!("{}/real", dir), format!("{}/other/link", dir)).unwrap();
+    let other = format!("{}/other", dir);
+
+    let follow = handle(
+        change_directory(
+            app::App::new(config::Config::default(), other.clone()),
+            &other,
+        ),
+        app::ExternalMsg::Enter,
+    );
+    assert_eq!(follow.pwd(), &format!("{}/real", dir));
+
+    let mut config = config::Config::default();
+    config.general.symlink_resolution = app::SymlinkResolution::DontFollow;
+    let app = app::App::new(config, other.clone());
+    let app = handle(app, app::ExternalMsg::ChangeDirectory(other.clone()));
+    let buffer =
+        explorer::explore_sync(app.explorer_config().clone(), other.clone(), None).unwrap();
+    let app = handle_internal(app, app::InternalMsg::AddDirectory(other.clone(), buffer));
+
+    let app = handle(app, app::ExternalMsg::Enter);
+    assert_eq!(app.pwd(), &format!("{}/link", other));
+
+    let app = handle(app, app::ExternalMsg::Back);
+    assert_eq!(app.pwd(), &other);
+}
+
+#[test]
+fn test_explain_key() {
+    let mut config = config::Config::default();
+    config
+        .modes
+        .get_mut("search")
+        .unwrap()
+        .key_bindings
+        .on_alphabet = Some(config::Action {
+        help: None,
+        messages: vec![app::ExternalMsg::BufferInputFromKey],
+    });
+    let app = app::App::new(config, "/".into());
+    let app = handle(app, app::ExternalMsg::SwitchMode("search".into()));
+
+    let app = handle(app, app::ExternalMsg::ExplainKey("x".into()));
+    assert_eq!(
+        app.logs().last().unwrap().message,
+        "key x in mode search is handled by on_alphabet: [BufferInputFromKey]"
+    );
+
+    let app = handle(app, app::ExternalMsg::ExplainKey("ctrl-c".into()));
+    assert!(app
+        .logs()
+        .last()
+        .unwrap()
+        .message
+        .starts_with("key ctrl-c in mode search is handled by on_key.ctrl-c: "));
+}
+
+#[test]
+fn test_focus_next_unselected() {
+    let dir = create_dir("focus_next_unselected", &["a", "b", "c", "d"]);
+    let app = change_directory(app::App::new(config::Config::default(), dir.clone()), &dir);
+    let app = handle(app, app::ExternalMsg::ToggleSelectionAndFocusNext);
+    let app = handle(app, app::ExternalMsg::ToggleSelectionAndFocusNext);
+    let app = handle(app, app::ExternalMsg::FocusNext);
+    let app = handle(app, app::ExternalMsg::ToggleSelection);
+    assert_eq!(selected_names(&app), vec!["a", "b", "d"]);
+    let focused = |app: &app::App| app.focused_node().map(|n| n.relative_path.clone()).unwrap();
+
+    let app = handle(app, app::ExternalMsg::FocusNextUnselected);
+    assert_eq!(focused(&app), "c");
+    let app = handle(app, app::ExternalMsg::FocusFirst);
+    let app = handle(app, app::ExternalMsg::FocusPreviousUnselected);
+    assert_eq!(focused(&app), "c");
+
+    let app = handle(app, app::ExternalMsg::ToggleSelection);
+    let app = handle(app, app::ExternalMsg::FocusNextUnselected);
+    assert_eq!(focused(&app), "c");
+    assert_eq!(
+        app.logs().last().unwrap().message,
+        "no unselected node found"
+    );
+}
+
+#[test]
+fn test_config_validation() {
+    assert!(config::Config::default().validate().is_empty());
+
+    let mut config = config::Config::default();
+    config
+        .modes
+        .get_mut("go to")
+        .unwrap()
+        .key_bindings
+        .on_key
+        .insert(
+            "z".into(),
+            serde_yaml::from_str("messages: [{SwitchMode: missing}, {Call: {command: ''}}]")
+                .unwrap(),
+        );
+
+    let app = app::App::new(config, "/".into());
+    let errors = app
+        .logs()
+        .iter()
+        .filter(|l| l.level == app::LogLevel::Error)
+        .map(|l| l.message.clone())
+        .collect::<Vec<String>>();
+    assert_eq!(
+        errors,
+        vec![
+            "invalid config: mode go to, on_key.z: switches to unknown mode missing",
+            "invalid config: mode go to, on_key.z: calls an empty command",
+        ]
+    );
+}
+
+#[test]
+fn test_from_yaml_str() {
+    let dir = create_dir("from_yaml_str", &["a", "b", "c"]);
+    let app = change_directory(app::App::new(config::Config::default(), dir.clone()), &dir);
+    let app = handle(app, app::ExternalMsg::FocusNext);
+    let app = handle(app, app::ExternalMsg::Select);
+    let app = handle(app, app::ExternalMsg::SwitchMode("go to".into()));
+
+    let state = serde_yaml::to_string(&app).unwrap();
+    let loaded = app::App::from_yaml_str(&state).unwrap();
+    assert_eq!(loaded.pwd(), app.pwd());
+    assert_eq!(loaded.mode().name, "go to");
+    assert_eq!(loaded.selection(), app.selection());
+    assert_eq!(loaded.directory_buffer(), app.directory_buffer());
+    assert!(!loaded.directory_buffer().unwrap().is_truncated());
+    assert_eq!(loaded.pipe().mode_out, app.pipe().mode_out);
+
+    let loaded = handle(loaded, app::ExternalMsg::FocusNext);
+    assert_eq!(loaded.focused_node().unwrap().relative_path, "c");
+
+    // The state dumped by an older version, without the newer fields.
+    let mut older: serde_yaml::Value = serde_yaml::from_str(&state).unwrap();
+    let older_map = older.as_mapping_mut().unwrap();
+    older_map.remove(&"last_focus".into());
+    older_map.remove(&"last_selected_index".into());
+    let loaded = app::App::from_yaml_str(&serde_yaml::to_string(&older).unwrap()).unwrap();
+    assert!(loaded.last_focus().is_empty());
+    assert_eq!(loaded.selection(), app.selection());
+
+    assert!(app::App::from_yaml_str("pwd: [").is_err());
+}
+
+#[test]
+fn test_file_uris() {
+    assert_eq!(app::path_from_uri("/tmp/a b"), Ok("/tmp/a b".into()));
+    assert_eq!(
+        app::path_from_uri("file:///tmp/a%20b"),
+        Ok("/tmp/a b".into())
+    );
+    assert_eq!(
+        app::path_from_uri("file://localhost/tmp/x"),
+        Ok("/tmp/x".into())
+    );
+    assert_eq!(app::path_from_uri("file:///100%"), Ok("/100%".into()));
+    assert!(app::path_from_uri("https://example.com/x").is_err());
+
+    let dir = create_dir("file uris", &["a b/", "c"]);
+    let app = app::App::new(config::Config::default(), dir.clone());
+    let uri = format!("file://{}/a%20b", dir.replace(' ', "%20"));
+    let app = handle(app, app::ExternalMsg::ChangeDirectory(uri));
+    assert_eq!(app.pwd(), &format!("{}/a b", dir));
+
+    let app = handle(
+        app,
+        app::ExternalMsg::FocusPath(format!("file://{}/c", dir.replace(' ', "%20"))),
+    );
+    assert_eq!(app.pwd(), &dir);
+
+    let app = handle(app, app::ExternalMsg::FocusPath("ftp://host/c".into()));
+    assert_eq!(app.pwd(), &dir);
+    assert_eq!(app.logs().last().unwrap().level, app::LogLevel::Error);
+}
+
+#[test]
+fn test_default_explorer_config() {
+    let dir = create_dir("default_explorer_config", &[".a", "b.txt", "c.md", "d.txt"]);
+
+    let mut config = config::Config::default();
+    config.general.default_explorer_config = Some(
+        serde_yaml::from_str(
+            "
+            filters: [{filter: RelativePathDoesNotEndWith, input: .md}]
+            sorters: [{sorter: ByRelativePath, reverse: true}]
+            ",
+        )
+        .unwrap(),
+    );
+
+    let app = app::App::new(config, dir.clone());
+    let defaults = app.explorer_config().clone();
+    let buf = explorer::explore_sync(defaults.clone(), dir.clone(), None).unwrap();
+    let names = buf
+        .nodes
+        .iter()
+        .map(|n| n.relative_path.clone())
+        .collect::<Vec<String>>();
+    assert_eq!(names, vec!["d.txt", "b.txt", ".a"]);
+
+    let app = handle(
+        app,
+        app::ExternalMsg::AddNodeFilter(app::NodeFilterApplicable::new(
+            app::NodeFilter::RelativePathDoesNotStartWith,
+            ".".into(),
+            false,
+        )),
+    );
+    assert_ne!(app.explorer_config(), &defaults);
+    let app = handle(app, app::ExternalMsg::ResetNodeFilters);
+    assert_eq!(app.explorer_config(), &defaults);
+
+    let mut config = config::Config::default();
+    config.general.default_explorer_config = None;
+    config.general.show_hidden = true;
+    let app = app::App::new(config, dir);
+    assert!(app.explorer_config().filters().is_empty());
+}
+
+#[test]
+fn test_swap_focus() {
+    let dir = create_dir("swap_focus", &["a", "b", "c"]);
+    let app = change_directory(app::App::new(config::Config::default(), dir.clone()), &dir);
+    let names = |app: &app::App| {
+        app.directory_buffer()
+            .unwrap()
+            .nodes
+            .iter()
+            .map(|n| n.relative_path.clone())
+            .collect::<Vec<String>>()
+    };
+
+    let app = handle(app, app::ExternalMsg::SwapFocusWithPrevious);
+    assert_eq!(names(&app), vec!["a", "b", "c"]);
+    assert_eq!(app.focused_node().unwrap().relative_path, "a");
+
+    let app = handle(app, app::ExternalMsg::SwapFocusWithNext);
+    let app = handle(app, app::ExternalMsg::SwapFocusWithNext);
+    assert_eq!(names(&app), vec!["b", "c", "a"]);
+    assert_eq!(app.focused_node().unwrap().relative_path, "a");
+
+    let app = handle(app, app::ExternalMsg::SwapFocusWithNext);
+    assert_eq!(names(&app), vec!["b", "c", "a"]);
+
+    let app = handle(app, app::ExternalMsg::SwapFocusWithPrevious);
+    assert_eq!(names(&app), vec!["b", "a", "c"]);
+    assert_eq!(app.focused_node().unwrap().relative_path, "a");
+}
+
+#[test]
+fn test_enter_empty_directory() {
+    let dir = create_dir("enter_empty_directory", &["empty/"]);
+    let empty = format!("{}/empty", dir);
+    let app = change_directory(app::App::new(config::Config::default(), dir.clone()), &dir);
+    let app = handle(app, app::ExternalMsg::Enter);
+    let mut app = handle_internal(
+        app,
+        app::InternalMsg::AddDirectory(empty.clone(), directory_buffer(&empty, 0)),
+    );
+    while app.pop_msg_out().is_some() {}
+
+    assert_eq!(app.pwd(), &empty);
+    assert_eq!(app.directory_buffer().unwrap().focus, 0);
+    assert!(app.focused_node().is_none());
+    assert!(app.result().is_empty());
+    assert_eq!(app.result_str(), "");
+
+    let mut app = handle(app, app::ExternalMsg::FocusNext);
+    assert_eq!(app.directory_buffer().unwrap().focus, 0);
+    while app.pop_msg_out().is_some() {}
+
+    let mut app = handle(app, app::ExternalMsg::PrintResultAndQuit);
+    assert_eq!(app.pop_msg_out(), Some(app::MsgOut::PrintResultAndQuit));
+    assert_eq!(app.result_str(), "");
+}
+
+#[test]
+fn test_selection_grouped_by_parent() {
+    let dir = create_dir("selection_grouped_by_parent", &["a", "b", "sub/", "sub/c"]);
+    let sub = format!("{}/sub", dir);
+    let app = app::App::new(config::Config::default(), dir.clone());
+    let app = handle(
+        app,
+        app::ExternalMsg::SetSelection(vec![
+            format!("{}/c", sub),
+            format!("{}/a", dir),
+            format!("{}/b", dir),
+        ]),
+    );
+
+    let groups = app
+        .selection_grouped_by_parent()
+        .into_iter()
+        .map(|(parent, nodes)| {
+            (
+                parent,
+                nodes
+                    .iter()
+                    .map(|n| n.relative_path.clone())
+                    .collect::<Vec<String>>(),
+            )
+        })
+        .collect::<Vec<(String, Vec<String>)>>();
+
+    assert_eq!(
+        groups,
+        vec![
+            (dir, vec!["a".to_string(), "b".to_string()]),
+            (sub, vec!["c".to_string()]),
+        ]
+    );
+}
+
+#[test]
+fn test_tick_advances_animations() {
+    let mut app = app::App::new(
+        config::Config::default(),
+        std::env::temp_dir().to_string_lossy().into(),
+    );
+    while app.pop_msg_out().is_some() {}
+
+    let app = handle_internal(app, app::InternalMsg::Tick);
+    assert_eq!(app.msg_out_len(), 0);
+    assert_eq!(app.animation_frame("spinner"), None);
+
+    let app = app.register_animation("spinner");
+    assert_eq!(app.animation_frame("spinner"), Some(0));
+    let app = handle_internal(app, app::InternalMsg::Tick);
+    let mut app = handle_internal(app, app::InternalMsg::Tick);
+    assert_eq!(app.animation_frame("spinner"), Some(2));
+    assert_eq!(app.pop_msg_out(), Some(app::MsgOut::Refresh));
+
+    let app = app.unregister_animation("spinner");
+    assert_eq!(app.animation_frame("spinner"), None);
+}
+
+#[cfg(feature = "checksum")]
+#[test]
+fn test_focus_next_duplicate() {
+    let dir = create_dir("focus_next_duplicate", &["a", "b", "c"]);
+    fs::write(PathBuf::from(&dir).join("a"), "same").unwrap();
+    fs::write(PathBuf::from(&dir).join("b"), "other").unwrap();
+    fs::write(PathBuf::from(&dir).join("c"), "same").unwrap();
+
+    let mut app = change_directory(app::App::new(config::Config::default(), dir.clone()), &dir);
+    for _ in 0..3 {
+        app = handle(app, app::ExternalMsg::ComputeFocusedChecksum);
+        app = handle(app, app::ExternalMsg::FocusNext);
+    }
+    let digest = |app: &app::App, name: &str| {
+        app.checksums()
+            .get(&format!("{}/{}", dir, name))
+            .map(|c| c.digest.clone())
+    };
+    assert_eq!(
+        digest(&app, "a"),
+        Some("0967115f2813a3541eaef77de9d9d5773f1c0c04314b0bbfe4ff3b3b1c55b5d5".to_string())
+    );
+    assert_eq!(digest(&app, "a"), digest(&app, "c"));
+
+    let app = handle(app, app::ExternalMsg::FocusFirst);
+    let app = handle(app, app::ExternalMsg::FocusNextDuplicate);
+    assert_eq!(app.focused_node().unwrap().relative_path, "c");
+    let app = handle(app, app::ExternalMsg::FocusNextDuplicate);
+    assert_eq!(app.focused_node().unwrap().relative_path, "a");
+
+    let app = handle(app, app::ExternalMsg::FocusNext);
+    let app = handle(app, app::ExternalMsg::FocusNextDuplicate);
+    assert_eq!(app.focused_node().unwrap().relative_path, "b");
+    assert_eq!(app.logs().last().unwrap().message, "no duplicate found");
+
+    // An outdated checksum is no longer a duplicate.
+    fs::write(PathBuf::from(&dir).join("c"), "not the same").unwrap();
+    let app = handle(app, app::ExternalMsg::FocusFirst);
+    let app = handle(app, app::ExternalMsg::FocusNextDuplicate);
+    assert_eq!(app.focused_node().unwrap().relative_path, "a");
+    assert_eq!(app.logs().last().unwrap().message, "no duplicate found");
+
+    let app = handle(app, app::ExternalMsg::HardRefresh);
+    assert!(app.checksums().is_empty());
+}
+
+#[test]
+fn test_hidden_file_patterns() {
+    let dir = create_dir(
+        "hidden_file_patterns",
+        &[".a", "b.pyc", "__pycache__/", "c.py"],
+    );
+    let names = |app: &app::App| {
+        explorer::explore_sync(app.explorer_config().clone(), dir.clone(), None)
+            .unwrap()
+            .nodes
+            .iter()
+            .map(|n| n.relative_path.clone())
+            .collect::<Vec<String>>()
+    };
+
+    let mut config = config::Config::default();
+    config.general.hidden_file_patterns = vec!["*.pyc".into(), "__pycache__".into()];
+    let app = app::App::new(config, dir.clone());
+    assert_eq!(names(&app), vec!["c.py"]);
+    let defaults = app.explorer_config().clone();
+
+    let app = handle(app, app::ExternalMsg::ToggleHidden);
+    let mut all = names(&app);
+    all.sort();
+    assert_eq!(all, vec![".a", "__pycache__", "b.pyc", "c.py"]);
+
+    let app = handle(app, app::ExternalMsg::ToggleHidden);
+    assert_eq!(app.explorer_config(), &defaults);
+
+    let app = handle(app, app::ExternalMsg::ToggleHidden);
+    let app = handle(app, app::ExternalMsg::ResetNodeFilters);
+    assert_eq!(app.explorer_config(), &defaults);
+}
+
+#[test]
+fn test_selection_stats() {
+    let dir = create_dir("selection_stats", &["a", "b", "c/"]);
+    fs::write(PathBuf::from(&dir).join("a"), "12345").unwrap();
+    fs::write(PathBuf::from(&dir).join("b"), "123").unwrap();
+    std::os::unix::fs::symlink(PathBuf::from(&dir).join("a"), PathBuf::from(&dir).join("d"))
+        .unwrap();
+
+    let app = change_directory(app::App::new(config::Config::default(), dir.clone()), &dir);
+    let stats = app.selection_stats_str();
+    assert_eq!(
+        stats,
+        r#"{"count":0,"total_bytes":0,"directories":0,"files":0,"symlinks":0}"#
+    );
+
+    let app = handle(
+        app,
+        app::ExternalMsg::SetSelection(
+            ["a", "b", "c", "d"]
+                .iter()
+                .map(|n| format!("{}/{}", dir, n))
+                .collect(),
+        ),
+    );
+    let stats: serde_json::Value = serde_json::from_str(&app.selection_stats_str()).unwrap();
+    assert_eq!(stats["count"], 4);
+    assert_eq!(stats["total_bytes"], 13);
+    assert_eq!(stats["directories"], 1);
+    assert_eq!(stats["files"], 3);
+    assert_eq!(stats["symlinks"], 1);
+
+    let mut app = app;
+    while app.pop_msg_out().is_some() {}
+    let mut app = handle(app, app::ExternalMsg::PrintSelectionStatsAndQuit);
+    assert_eq!(
+        app.pop_msg_out(),
+        Some(app::MsgOut::PrintSelectionStatsAndQuit)
+    );
+}
+
+#[test]
+fn test_focus_path_in_pwd() {
+    let dir = create_dir("focus_path_in_pwd", &["a", "b", "c"]);
+    let mut app = change_directory(app::App::new(config::Config::default(), dir.clone()), &dir);
+    while app.pop_msg_out().is_some() {}
+
+    let mut app = handle(app, app::ExternalMsg::FocusPath(format!("{}/c", dir)));
+    assert_eq!(app.pwd(), &dir);
+    assert_eq!(app.focused_node().unwrap().relative_path, "c");
+    assert_eq!(app.pop_msg_out(), Some(app::MsgOut::Refresh));
+    assert_eq!(app.pop_msg_out(), None);
+
+    // Nothing to focus, nothing to refresh.
+    let mut app = handle(app, app::ExternalMsg::FocusPath(format!("{}/d", dir)));
+    assert_eq!(app.focused_node().unwrap().relative_path, "c");
+    assert_eq!(app.pop_msg_out(), None);
+}
+
+#[test]
+fn test_focus_by_absolute_path_survives_repeated_exploration() {
+    let dir = create_dir(
+        "focus_by_absolute_path_survives_repeated_exploration",
+        &["a", "b", "c"],
+    );
+    let app = change_directory(
+        app::App::new(config::Config::default(), dir.clone()),
+        &std::env::temp_dir().to_string_lossy(),
+    );
+
+    let app = handle(
+        app,
+        app::ExternalMsg::FocusByAbsolutePath(format!("{}/c", dir)),
+    );
+    assert_eq!(app.pwd(), &dir);
+    let focus = app.explore_focus();
+    assert_eq!(focus, Some("c".into()));
+
+    // E.g. `Refresh` and `Explore` in the same pass.
+    let config = app.explorer_config().clone();
+    let first = explorer::explore_sync(config.clone(), dir.clone(), focus.clone()).unwrap();
+    let second = explorer::explore_sync(config, dir.clone(), focus).unwrap();
+
+    let app = handle_internal(app, app::InternalMsg::AddDirectory(dir.clone(), first));
+    assert_eq!(app.focused_node().unwrap().relative_path, "c");
+    let app = handle_internal(app, app::InternalMsg::AddDirectory(dir.clone(), second));
+    assert_eq!(app.focused_node().unwrap().relative_path, "c");
+
+    // Until a buffer has the node, the focus stays pending.
+    let other = create_dir(
+        "focus_by_absolute_path_survives_repeated_exploration_other",
+        &["d"],
+    );
+    let app = handle(
+        app,
+        app::ExternalMsg::FocusByAbsolutePath(format!("{}/d", other)),
+    );
+    let app = handle_internal(
+        app,
+        app::InternalMsg::AddDirectory(
+            other.clone(),
+            app::DirectoryBuffer::new(other.clone(), vec![], vec![], 0),
+        ),
+    );
+    assert_eq!(app.explore_focus(), Some("d".into()));
+    let app = handle_internal(
+        app,
+        app::InternalMsg::AddDirectory(other.clone(), directory_buffer(&other, 0)),
+    );
+    assert_eq!(app.focused_node().unwrap().relative_path, "d");
+    assert_eq!(app.explore_focus(), Some("d".into()));
+}
+
+#[test]
+fn test_log_entering_empty_directory() {
+    let dir = create_dir("log_entering_empty_directory", &["a/"]);
+    let empty = format!("{}/a", dir);
+    let enter = |app: app::App| {
+        let app = handle(app, app::ExternalMsg::Enter);
+        handle_internal(
+            app,
+            app::InternalMsg::AddDirectory(empty.clone(), directory_buffer(&empty, 0)),
+        )
+    };
+
+    let app = enter(change_directory(
+        app::App::new(config::Config::default(), dir.clone()),
+        &dir,
+    ));
+    assert_eq!(app.pwd(), &empty);
+    let log = app.logs().last().unwrap();
+    assert_eq!(log.level, app::LogLevel::Info);
+    assert_eq!(log.message, "empty directory");
+
+    let mut config = config::Config::default();
+    config.general.follow_focus_into_empty = false;
+    let app = change_directory(app::App::new(config, dir.clone()), &dir);
+    let logs = app.logs().len();
+    let app = enter(app);
+    assert_eq!(app.pwd(), &empty);
+    assert_eq!(app.logs().len(), logs);
+}
+
+#[test]
+fn test_clear_selection_in_current_directory() {
+    let dir = create_dir(
+        "clear_selection_in_current_directory",
+        &["a", "b", "c/", "c/d"],
+    );
+    let app = handle(
+        app::App::new(config::Config::default(), dir.clone()),
+        app::ExternalMsg::SetSelection(vec![
+            format!("{}/a", dir),
+            format!("{}/b", dir),
+            format!("{}/c/d", dir),
+        ]),
+    );
+    assert_eq!(app.selection().len(), 3);
+
+    let app = change_directory(app, &dir);
+    let app = handle(app, app::ExternalMsg::ClearSelectionInCurrentDirectory);
+    let selection = app
+        .selection()
+        .iter()
+        .map(|n| n.absolute_path.clone())
+        .collect::<Vec<String>>();
+    assert_eq!(selection, vec![format!("{}/c/d", dir)]);
+}
+
+#[test]
+fn test_focus_path_or_create() {
+    let dir = create_dir("focus_path_or_create", &["a"]);
+    let app = change_directory(app::App::new(config::Config::default(), dir.clone()), &dir);
+
+    let app = handle(app, app::ExternalMsg::FocusPathOrCreate("b/c/d.txt".into()));
+    assert!(PathBuf::from(&dir).join("b/c/d.txt").is_file());
+    assert_eq!(app.pwd(), &format!("{}/b/c", dir));
+
+    let app = handle_internal(
+        app,
+        app::InternalMsg::AddDirectory(
+            format!("{}/b/c", dir),
+            directory_buffer(&format!("{}/b/c", dir), 0),
+        ),
+    );
+    assert_eq!(app.focused_node().unwrap().relative_path, "d.txt");
+    let messages = app
+        .logs()
+        .iter()
+        .rev()
+        .take(2)
+        .map(|l| l.message.clone())
+        .collect::<Vec<String>>();
+    assert_eq!(
+        messages,
+        vec![
+            format!("created file {}/b/c/d.txt", dir),
+            format!("created directory {}/b/c", dir),
+        ]
+    );
+
+    let app = change_directory(app, &dir);
+    let app = handle(
+        app,
+        app::ExternalMsg::FocusPathOrCreate(format!("{}/e/", dir)),
+    );
+    assert!(PathBuf::from(&dir).join("e").is_dir());
+    assert_eq!(app.pwd(), &dir);
+
+    let logs = app.logs().len();
+    let app = handle(
+        app,
+        app::ExternalMsg::FocusPathOrCreate("../../../outside".into()),
+    );
+    assert_eq!(app.logs().len(), logs + 1);
+    assert_eq!(app.logs().last().unwrap().level, app::LogLevel::Error);
+    assert!(!PathBuf::from(&dir).join("../outside").exists());
+}
+
+#[test]
+fn test_node_at_and_focused_index() {
+    let app = app::App::new(
+        config::Config::default(),
+        std::env::temp_dir().to_string_lossy().into(),
+    );
+    assert_eq!(app.focused_index(), None);
+    assert!(app.node_at(0).is_none());
+
+    let dir = create_dir("node_at_and_focused_index", &["a", "b"]);
+    let app = change_directory(app, &dir);
+    let app = handle(app, app::ExternalMsg::FocusNext);
+    assert_eq!(app.focused_index(), Some(1));
+    assert_eq!(app.node_at(0).unwrap().relative_path, "a");
+    assert_eq!(app.node_at(1), app.focused_node());
+    assert!(app.node_at(2).is_none());
+}
+
+#[test]
+fn test_rename_selection_with_template() {
+    let dir = create_dir("rename_selection_with_template", &["b.txt", "a.md", "c"]);
+    let select = |app: app::App, names: &[&str]| {
+        handle(
+            app,
+            app::ExternalMsg::SetSelection(
+                names.iter().map(|n| format!("{}/{}", dir, n)).collect(),
+            ),
+        )
+    };
+    let names = || {
+        let mut names = fs::read_dir(&dir)
+            .unwrap()
+            .map(|e| e.unwrap().file_name().to_string_lossy().to_string())
+            .collect::<Vec<String>>();
+        names.sort();
+        names
+    };
+
+    let app = select(
+        app::App::new(config::Config::default(), dir.clone()),
+        &["b.txt", "a.md"],
+    );
+    let mut app = handle(
+        app,
+        app::ExternalMsg::RenameSelectionWithTemplate("{n:03}_{stem}.{ext}".into()),
+    );
+    assert_eq!(names(), vec!["001_b.txt", "002_a.md", "c"]);
+    assert_eq!(app.logs().last().unwrap().message, "renamed 2 nodes");
+    assert_eq!(
+        app.selection()
+            .iter()
+            .map(|n| n.relative_path.clone())
+            .collect::<Vec<String>>(),
+        vec!["001_b.txt", "002_a.md"]
+    );
+    while app.pop_msg_out() != Some(app::MsgOut::Explore) {}
+
+    // Clashing with each other.
+    let app = select(app, &["001_b.txt", "002_a.md"]);
+    let app = handle(
+        app,
+        app::ExternalMsg::RenameSelectionWithTemplate("same".into()),
+    );
+    assert_eq!(app.logs().last().unwrap().level, app::LogLevel::Error);
+    assert_eq!(names(), vec!["001_b.txt", "002_a.md", "c"]);
+
+    // Clashing with an existing node.
+    let app = handle(
+        app,
+        app::ExternalMsg::RenameSelectionWithTemplate("{n}{name}".into()),
+    );
+    assert_eq!(names(), vec!["1001_b.txt", "2002_a.md", "c"]);
+    let app = select(app, &["1001_b.txt"]);
+    let app = handle(
+        app,
+        app::ExternalMsg::RenameSelectionWithTemplate("c".into()),
+    );
+    assert_eq!(app.logs().last().unwrap().level, app::LogLevel::Error);
+    assert_eq!(names(), vec!["1001_b.txt", "2002_a.md", "c"]);
+
+    // Without an extension, there's no dot to separate it.
+    let app = select(app, &["c"]);
+    handle(
+        app,
+        app::ExternalMsg::RenameSelectionWithTemplate("{n:03}_{stem}.{ext}".into()),
+    );
+    assert_eq!(names(), vec!["001_c", "1001_b.txt", "2002_a.md"]);
+}
+
+#[test]
+fn test_confirm_destructive() {
+    let dir = create_dir("confirm_destructive", &["a", "b"]);
+    let names = || {
+        let mut names = fs::read_dir(&dir)
+            .unwrap()
+            .map(|e| e.unwrap().file_name().to_string_lossy().to_string())
+            .collect::<Vec<String>>();
+        names.sort();
+        names
+    };
+    let rename = app::ExternalMsg::RenameSelectionWithTemplate("{n}_{name}".into());
+
+    let mut config = config::Config::default();
+    config.general.confirm_destructive = true;
+    let app = change_directory(app::App::new(config, dir.clone()), &dir);
+    let app = handle(app, app::ExternalMsg::Select);
+
+    let app = handle(app, rename.clone());
+    assert_eq!(app.pending_confirmation(), Some(&rename));
+    assert_eq!(names(), vec!["a", "b"]);
+
+    let app = handle(app, app::ExternalMsg::CancelPending);
+    assert_eq!(app.pending_confirmation(), None);
+    let app = handle(app, app::ExternalMsg::ConfirmPending);
+    assert_eq!(names(), vec!["a", "b"]);
+
+    let app = handle(app, rename.clone());
+    let app = handle(app, app::ExternalMsg::ConfirmPending);
+    assert_eq!(app.pending_confirmation(), None);
+    assert_eq!(names(), vec!["1_a", "b"]);
+
+    // Without the flag, it acts immediately.
+    let app = change_directory(app::App::new(config::Config::default(), dir.clone()), &dir);
+    let app = handle(app, app::ExternalMsg::FocusLast);
+    let app = handle(app, app::ExternalMsg::Select);
+    let app = handle(app, rename);
+    assert_eq!(app.pending_confirmation(), None);
+    assert_eq!(names(), vec!["1_a", "1_b"]);
+}
+
+#[test]
+fn test_explore_symlinks_as_dirs() {
+    let dir = create_dir("explore_symlinks_as_dirs", &["real/", "real/a"]);
+    std::os::unix::fs::symlink(
+        PathBuf::from(&dir).join("real"),
+        PathBuf::from(&dir).join("link"),
+    )
+    .unwrap();
+    let enter_link = |config: config::Config| {
+        let app = change_directory(app::App::new(config, dir.clone()), &dir);
+        let app = handle(app, app::ExternalMsg::FocusPath(format!("{}/link", dir)));
+        let node = app.focused_node().unwrap();
+        assert!(node.is_dir);
+        assert!(node.is_symlink);
+        handle(app, app::ExternalMsg::Enter)
+    };
+
+    let app = enter_link(config::Config::default());
+    assert_eq!(app.pwd(), &format!("{}/real", dir));
+
+    let mut config = config::Config::default();
+    config.general.explore_symlinks_as_dirs = true;
+    let app = enter_link(config);
+    assert_eq!(app.pwd(), &format!("{}/link", dir));
+    let app = handle(app, app::ExternalMsg::Back);
+    assert_eq!(app.pwd(), &dir);
+}
+
+#[test]
+fn test_print_focused_and_selection() {
+    let dir = create_dir("print_focused_and_selection", &["a", "b", "c"]);
+    let app = change_directory(app::App::new(config::Config::default(), dir.clone()), &dir);
+    let app = handle(
+        app,
+        app::ExternalMsg::SetSelection(vec![format!("{}/b", dir), format!("{}/c", dir)]),
+    );
+    let mut app = handle(app, app::ExternalMsg::PrintFocusedAndSelectionAndQuit);
+    let msg_out = std::iter::from_fn(|| app.pop_msg_out()).collect::<Vec<app::MsgOut>>();
+    assert!(msg_out.contains(&app::MsgOut::PrintFocusedAndSelectionAndQuit));
+
+    let out: serde_json::Value = serde_json::from_str(&app.focused_and_selection_str()).unwrap();
+    assert_eq!(out["focused"], format!("{}/a", dir));
+    assert_eq!(
+        out["selection"],
+        serde_json::json!([format!("{}/b", dir), format!("{}/c", dir)])
+    );
+
+    let app = app::App::new(config::Config::default(), dir.clone());
+    assert_eq!(
+        app.focused_and_selection_str(),
+        r#"{"focused":null,"selection":[]}"#
+    );
+}
+
+#[test]
+fn test_self_check() {
+    let dir = create_dir("self_check", &["a", "b", "c"]);
+    let app = change_directory(app::App::new(config::Config::default(), dir.clone()), &dir);
+    let app = handle(app, app::ExternalMsg::SelfCheckAndLog);
+    assert_eq!(
+        app.logs().last().unwrap().message,
+        "self check found no inconsistencies"
+    );
+
+    let app = handle(
+        app,
+        app::ExternalMsg::SetSelection(vec![format!("{}/a", dir), format!("{}/b", dir)]),
+    );
+    fs::remove_file(PathBuf::from(&dir).join("b")).unwrap();
+    let app = handle_internal(
+        app,
+        app::InternalMsg::AddDirectory(dir.clone(), {
+            let mut buf = directory_buffer(&dir, 0);
+            buf.focus = 12;
+            buf
+        }),
+    );
+    assert_eq!(
+        app.validate(),
+        vec![
+            format!("focus=12 but total=2 in {}", dir),
+            format!("selected {}/b doesn't exist", dir),
+        ]
+    );
+
+    let logs = app.logs().len();
+    let app = handle(app, app::ExternalMsg::SelfCheckAndLog);
+    assert_eq!(app.logs().len(), logs + 2);
+    assert_eq!(app.logs().last().unwrap().level, app::LogLevel::Warning);
+}
+
+#[test]
+fn test_change_directory_relative() {
+    let dir = create_dir("change_directory_relative", &["sub/", "sub/inner/", "file"]);
+    let app = change_directory(app::App::new(config::Config::default(), dir.clone()), &dir);
+
+    let app = handle(app, app::ExternalMsg::ChangeDirectory("./sub".into()));
+    assert_eq!(app.pwd(), &format!("{}/sub", dir));
+
+    let app = handle(app, app::ExternalMsg::ChangeDirectory("inner".into()));
+    assert_eq!(app.pwd(), &format!("{}/sub/inner", dir));
+
+    let app = handle(app, app::ExternalMsg::ChangeDirectory("../..".into()));
+    assert_eq!(app.pwd(), &dir);
+
+    let app = handle(app, app::ExternalMsg::ChangeDirectory("..".into()));
+    assert_eq!(
+        app.pwd(),
+        &PathBuf::from(&dir)
+            .parent()
+            .unwrap()
+            .to_string_lossy()
+            .to_string()
+    );
+
+    let app = handle(
+        app,
+        app::ExternalMsg::ChangeDirectory(format!("{}/sub", dir)),
+    );
+    assert_eq!(app.pwd(), &format!("{}/sub", dir));
+
+    // Not a directory, or not there.
+    let app = handle(app, app::ExternalMsg::ChangeDirectory("../file".into()));
+    let app = handle(app, app::ExternalMsg::ChangeDirectory("missing".into()));
+    assert_eq!(app.pwd(), &format!("{}/sub", dir));
+}
+
+#[test]
+fn test_back_at_root() {
+    let app = handle(
+        app::App::new(
+            config::Config::default(),
+            std::env::temp_dir().to_string_lossy().into(),
+        ),
+        app::ExternalMsg::ChangeDirectory("/".into()),
+    );
+    let app = handle(app, app::ExternalMsg::Back);
+    assert_eq!(app.pwd(), "/");
+    let log = app.logs().last().unwrap();
+    assert_eq!(log.level, app::LogLevel::Info);
+    assert_eq!(log.message, "already at root");
+}
+
+#[test]
+fn test_input_history() {
+    let dir = create_dir("input_history", &["a", "b"]);
+    let app = change_directory(app::App::new(config::Config::default(), dir.clone()), &dir);
+    let app = handle(app, app::ExternalMsg::SetInputBuffer(format!("{}/b", dir)));
+    let app = handle(app, app::ExternalMsg::FocusPathFromInput);
+    let app = handle(app, app::ExternalMsg::SetInputBuffer("a".into()));
+    let app = handle(
+        app,
+        app::ExternalMsg::AddNodeFilterFromInput(
+            serde_yaml::from_str("{filter: RelativePathDoesStartWith}").unwrap(),
+        ),
+    );
+    let app = handle(app, app::ExternalMsg::ResetInputBuffer);
+    assert_eq!(
+        app.input_history().iter().cloned().collect::<Vec<String>>(),
+        vec![format!("{}/b", dir), "a".to_string()]
+    );
+
+    let app = handle(app, app::ExternalMsg::RecallPreviousInput);
+    assert_eq!(app.input_buffer(), Some("a".into()));
+    let app = handle(app, app::ExternalMsg::RecallPreviousInput);
+    assert_eq!(app.input_buffer(), Some(format!("{}/b", dir)));
+    // Stays at the oldest.
+    let app = handle(app, app::ExternalMsg::RecallPreviousInput);
+    assert_eq!(app.input_buffer(), Some(format!("{}/b", dir)));
+
+    let app = handle(app, app::ExternalMsg::RecallNextInput);
+    assert_eq!(app.input_buffer(), Some("a".into()));
+    let app = handle(app, app::ExternalMsg::RecallNextInput);
+    assert_eq!(app.input_buffer(), Some("".into()));
+
+    let mut config = config::Config::default();
+    config.general.input_history_size = 1;
+    let app = app::App::new(config, dir.clone());
+    let app = handle(app, app::ExternalMsg::SetInputBuffer("x".into()));
+    let app = handle(app, app::ExternalMsg::FocusPathFromInput);
+    let app = handle(app, app::ExternalMsg::SetInputBuffer("y".into()));
+    let app = handle(app, app::ExternalMsg::FocusPathFromInput);
+    assert_eq!(
+        app.input_history().iter().cloned().collect::<Vec<String>>(),
+        vec!["y".to_string()]
+    );
+}
+
+#[test]
+fn test_select_to_anchor() {
+    let dir = create_dir("select_to_anchor", &["a", "b", "c", "d", "e"]);
+    let selected = |app: &app::App| {
+        let mut names = app
+            .selection()
+            .iter()
+            .map(|n| n.relative_path.clone())
+            .collect::<Vec<String>>();
+        names.sort();
+        names
+    };
+
+    let app = change_directory(app::App::new(config::Config::default(), dir.clone()), &dir);
+    let app = handle(app, app::ExternalMsg::SelectToAnchor);
+    assert!(app.selection().is_empty());
+
+    let app = handle(app, app::ExternalMsg::FocusByIndex(1));
+    let app = handle(app, app::ExternalMsg::SetSelectionAnchor);
+    assert_eq!(app.selection_anchor(), Some(1));
+
+    // Forward.
+    let app = handle(app, app::ExternalMsg::FocusByIndex(3));
+    let app = handle(app, app::ExternalMsg::SelectToAnchor);
+    assert_eq!(selected(&app), vec!["b", "c", "d"]);
+    assert_eq!(app.selection_anchor(), Some(1));
+
+    // Backward, from a moved anchor.
+    let app = handle(app, app::ExternalMsg::ClearSelection);
+    let app = handle(app, app::ExternalMsg::FocusByIndex(4));
+    let app = handle(app, app::ExternalMsg::SetSelectionAnchor);
+    let app = handle(app, app::ExternalMsg::FocusByIndex(2));
+    let app = handle(app, app::ExternalMsg::SelectToAnchor);
+    assert_eq!(selected(&app), vec!["c", "d", "e"]);
+
+    let app = handle(app, app::ExternalMsg::ClearSelectionAnchor);
+    assert_eq!(app.selection_anchor(), None);
+}
+
+#[test]
+fn test_auto_select_on_enter_single() {
+    let dir = create_dir("auto_select_on_enter_single", &["a.md", "b.rs", "c.rs"]);
+    let add_directory = |app: app::App| {
+        let buf = explorer::explore_sync(app.explorer_config().clone(), dir.clone(), None).unwrap();
+        handle_internal(app, app::InternalMsg::AddDirectory(dir.clone(), buf))
+            .possibly_mutate()
+            .unwrap()
+    };
+    let explore = |app: app::App| add_directory(handle(app, app::ExternalMsg::Explore));
+    let filter = app::ExternalMsg::AddNodeFilter(app::NodeFilterApplicable::new(
+        app::NodeFilter::RelativePathDoesNotEndWith,
+        ".rs".into(),
+        false,
+    ));
+
+    let mut config = config::Config::default();
+    config.general.auto_select_on_enter_single = true;
+    let app = explore(app::App::new(config.clone(), dir.clone()));
+    let app = handle(app, app::ExternalMsg::FocusLast);
+    let app = explore(handle(app, filter.clone()));
+    assert_eq!(app.focused_node().unwrap().relative_path, "a.md");
+    assert!(app.selection().is_empty());
+    assert_eq!(
+        app.logs().last().unwrap().message,
+        "focused the only node a.md"
+    );
+
+    // Selecting is a separate opt-in.
+    config.general.auto_select_on_enter_single_messages = vec![app::ExternalMsg::Select];
+    let app = explore(handle(app::App::new(config, dir.clone()), filter.clone()));
+    assert_eq!(app.focused_node().unwrap().relative_path, "a.md");
+    assert_eq!(app.selection().len(), 1);
+
+    // Not selected again on the next refresh, e.g. after unselecting it.
+    let app = handle(app, app::ExternalMsg::UnSelect);
+    let app = add_directory(app);
+    assert!(app.selection().is_empty());
+
+    // Off by default.
+    let app = handle(
+        app::App::new(config::Config::default(), dir.clone()),
+        app::ExternalMsg::FocusLast,
+    );
+    let app = explore(handle(app, filter));
+    assert!(app.selection().is_empty());
+}
+
+#[test]
+fn test_show_only_mime_category() {
+    let dir = create_dir(
+        "show_only_mime_category",
+        &["a.png", "b.jpg", "c.mp4", "d.txt", "e/"],
+    );
+    let names = |app: &app::App| {
+        let mut names = explorer::explore_sync(app.explorer_config().clone(), dir.clone(), None)
+            .unwrap()
+            .nodes
+            .iter()
+            .map(|n| n.relative_path.clone())
+            .collect::<Vec<String>>();
+        names.sort();
+        names
+    };
+
+    let app = app::App::new(config::Config::default(), dir.clone());
+    let filters = app.explorer_config().filters().clone();
+
+    let mut app = handle(app, app::ExternalMsg::ShowOnlyMimeCategory("image".into()));
+    assert_eq!(names(&app), vec!["a.png", "b.jpg"]);
+    assert!(std::iter::from_fn(|| app.pop_msg_out()).any(|m| m == app::MsgOut::Explore));
+
+    // Replaces the previous category.
+    let app = handle(app, app::ExternalMsg::ShowOnlyMimeCategory("video/".into()));
+    assert_eq!(names(&app), vec!["c.mp4"]);
+    assert_eq!(app.explorer_config().filters(), &filters);
+
+    let app = handle(app, app::ExternalMsg::ShowAllMimeCategories);
+    assert_eq!(names(&app), vec!["a.png", "b.jpg", "c.mp4", "d.txt", "e"]);
+    assert!(app.explorer_config().mime_category_filter().is_none());
+}
+
+#[test]
+fn test_explore_on_change_directory() {
+    let dir = create_dir("explore_on_change_directory", &["a/"]);
+    let change = |config: config::Config| {
+        let mut app = handle(
+            app::App::new(config, "/".into()),
+            app::ExternalMsg::ChangeDirectory(dir.clone()),
+        );
+        std::iter::from_fn(|| app.pop_msg_out()).collect::<Vec<app::MsgOut>>()
+    };
+
+    assert_eq!(
+        change(config::Config::default()),
+        vec![app::MsgOut::Refresh]
+    );
+
+    let mut config = config::Config::default();
+    config.general.explore_on_change_directory = true;
+    assert_eq!(change(config), vec![app::MsgOut::Explore]);
+}
+
+#[test]
+fn test_focus_by_glob() {
+    let dir = create_dir("focus_by_glob", &["a.txt", "b.png", "c.md", "d.png"]);
+    let app = change_directory(app::App::new(config::Config::default(), dir.clone()), &dir);
+
+    let app = handle(app, app::ExternalMsg::FocusByGlob("*.png".into()));
+    assert_eq!(app.focused_node().unwrap().relative_path, "b.png");
+    assert_eq!(app.directory_buffer().unwrap().nodes.len(), 4);
+
+    let app = handle(app, app::ExternalMsg::FocusByGlob("*.gif".into()));
+    assert_eq!(app.focused_node().unwrap().relative_path, "b.png");
+    assert_eq!(app.logs().last().unwrap().message, "no node matches *.gif");
+
+    let app = handle(app, app::ExternalMsg::FocusByGlob("[cd].md".into()));
+    assert_eq!(app.focused_node().unwrap().relative_path, "b.png");
+    assert_eq!(app.logs().last().unwrap().level, app::LogLevel::Error);
+
+    let app = handle(app, app::ExternalMsg::SetInputBuffer("?.md".into()));
+    let app = handle(app, app::ExternalMsg::FocusByGlobFromInput);
+    assert_eq!(app.focused_node().unwrap().relative_path, "c.md");
+}
+
+#[test]
+fn test_result_uses_path_display() {
+    let dir = create_dir("result_uses_path_display", &["a", "b"]);
+    let mut config = config::Config::default();
+    config.general.path_display = app::PathDisplay::RelativeToPwd;
+    let app = change_directory(app::App::new(config.clone(), dir.clone()), &dir);
+    let app = handle(
+        app,
+        app::ExternalMsg::SetSelection(vec![format!("{}/a", dir), format!("{}/b", dir)]),
+    );
+    assert_eq!(app.result_str(), format!("{0}/a\n{0}/b", dir));
+
+    config.general.result_uses_path_display = true;
+    let app = change_directory(app::App::new(config, dir.clone()), &dir);
+    let app = handle(
+        app,
+        app::ExternalMsg::SetSelection(vec![format!("{}/a", dir), format!("{}/b", dir)]),
+    );
+    assert_eq!(app.result_str(), "a\nb");
+}
+
+#[test]
+fn test_reset() {
+    let dir = create_dir("reset", &["a", "b", "c/"]);
+    let app = change_directory(app::App::new(config::Config::default(), dir.clone()), &dir);
+    let initial = app.explorer_config().clone();
+
+    let app = handle(
+        app,
+        app::ExternalMsg::SetSelection(vec![format!("{}/a", dir)]),
+    );
+    let app = handle(app, app::ExternalMsg::ShowOnlyMimeCategory("image".into()));
+    let app = handle(app, app::ExternalMsg::ToggleHidden);
+    let app = handle(app, app::ExternalMsg::SwitchMode("search".into()));
+    let app = handle(app, app::ExternalMsg::SetInputBuffer("foo".into()));
+    let app = handle(app, app::ExternalMsg::LogInfo("foo".into()));
+    let app = handle(app, app::ExternalMsg::ChangeDirectory(format!("{}/c", dir)));
+    assert_ne!(app.explorer_config(), &initial);
+
+    let mut app = handle(app, app::ExternalMsg::Reset);
+    assert_eq!(app.pwd(), &dir);
+    assert_eq!(app.mode().name, "default");
+    assert!(app.selection().is_empty());
+    assert_eq!(app.explorer_config(), &initial);
+    assert_eq!(app.input_buffer(), None);
+    assert!(app.logs().is_empty());
+    assert!(std::iter::from_fn(|| app.pop_msg_out()).any(|m| m == app::MsgOut::Explore));
+}
+
+#[test]
+fn test_reset_runtime_state() {
+    let dir = create_dir("reset_runtime_state", &["a", "b/"]);
+    let mut config = config::Config::default();
+    config.general.explore_on_change_directory = true;
+    config.general.confirm_destructive = true;
+    let app = change_directory(app::App::new(config, dir.clone()), &dir);
+
+    let app = handle(app, app::ExternalMsg::Select);
+    let app = handle(
+        app,
+        app::ExternalMsg::RenameSelectionWithTemplate("{n}".into()),
+    );
+    let app = handle(app, app::ExternalMsg::BeginBatch);
+    let mut app = handle(app, app::ExternalMsg::ChangeDirectory(format!("{}/b", dir)));
+    assert!(app.pending_confirmation().is_some());
+    while app.pop_msg_out().is_some() {}
+
+    let mut app = handle(app, app::ExternalMsg::Reset);
+    assert_eq!(app.pwd(), &dir);
+    assert_eq!(app.pending_confirmation(), None);
+    let msgs = std::iter::from_fn(|| app.pop_msg_out()).collect::<Vec<app::MsgOut>>();
+    assert_eq!(msgs, vec![app::MsgOut::Explore]);
+
+    // No longer in a batch.
+    let mut app = handle(app, app::ExternalMsg::FocusNext);
+    assert_eq!(app.pop_msg_out(), Some(app::MsgOut::Refresh));
+
+    // Nowhere to go back to.
+    let app = handle(app, app::ExternalMsg::ToggleLastDirectory);
+    assert_eq!(app.pwd(), &dir);
+}
+
+#[test]
+fn test_last_focus_yields_to_focus_by_name() {
+    let dir = create_dir("last_focus_by_name", &["a", "b", "c", "d"]);
+    let app = change_directory(app::App::new(config::Config::default(), dir.clone()), &dir);
+    let app = handle(app, app::ExternalMsg::FocusByIndex(2));
+    assert_eq!(app.last_focus().get(&dir), Some(&2));
+
+    // e.g. after a hard refresh, with a node added before the focused one.
+    fs::File::create(PathBuf::from(&dir).join("0")).unwrap();
+    let app = handle(app, app::ExternalMsg::HardRefresh);
+    let buf = explorer::explore_sync(app.explorer_config().clone(), dir.clone(), Some("c".into()))
+        .unwrap();
+    assert!(buf.focused_by_name);
+    let app = handle_internal(app, app::InternalMsg::AddDirectory(dir.clone(), buf));
+    assert_eq!(app.focused_node().unwrap().relative_path, "c");
+}
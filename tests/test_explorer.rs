@@ -0,0 +1,216 @@
+use std::fs;
+use xplr::*;
+
+mod common;
+use common::create_dir;
+
+#[test]
+fn test_explore_directories_first() {
+    let dir = create_dir("directories_first", &["e", "d/", "a", "f/", "c", "b/"]);
+    let names = |config: app::ExplorerConfig| {
+        explorer::explore_sync(config, dir.clone(), None)
+            .unwrap()
+            .nodes
+            .iter()
+            .map(|n| n.relative_path.clone())
+            .collect::<Vec<String>>()
+    };
+
+    let config: app::ExplorerConfig =
+        serde_yaml::from_str("{filters: [], directories_first: true}").unwrap();
+    assert_eq!(names(config), vec!["b", "d", "f", "a", "c", "e"]);
+
+    let config: app::ExplorerConfig = serde_yaml::from_str(
+        "{filters: [], sorters: [{sorter: ByRelativePath, reverse: true}], directories_first: true}",
+    )
+    .unwrap();
+    assert_eq!(names(config), vec!["f", "d", "b", "e", "c", "a"]);
+}
+
+#[test]
+fn test_explore_max_nodes() {
+    let dir = create_dir("max_nodes", &["a", "b", "c", "d", "e"]);
+
+    let config: app::ExplorerConfig = serde_yaml::from_str("{filters: [], max_nodes: 3}").unwrap();
+    let buf = explorer::explore_sync(config, dir.clone(), None).unwrap();
+
+    assert_eq!(buf.nodes.len(), 3);
+    assert_eq!(buf.total, 3);
+    assert_eq!(buf.raw_total, 5);
+    assert!(buf.is_truncated());
+
+    let config: app::ExplorerConfig = serde_yaml::from_str("{filters: [], max_nodes: 5}").unwrap();
+    let buf = explorer::explore_sync(config, dir, None).unwrap();
+
+    assert_eq!(buf.nodes.len(), 5);
+    assert_eq!(buf.total, 5);
+    assert_eq!(buf.raw_total, 5);
+    assert!(!buf.is_truncated());
+}
+
+#[test]
+fn test_explore_keeps_all_nodes() {
+    let dir = create_dir("all_nodes", &[".a", ".b", "c", "d"]);
+
+    let config: app::ExplorerConfig =
+        serde_yaml::from_str("{filters: [{filter: RelativePathDoesNotStartWith, input: .}]}")
+            .unwrap();
+    let buf = explorer::explore_sync(config.clone(), dir.clone(), None).unwrap();
+
+    assert_eq!(buf.nodes.len(), 2);
+    assert_eq!(buf.all_nodes.len(), 4);
+    assert_eq!(buf.total, 2);
+    assert_eq!(buf.raw_total, 4);
+    assert!(buf.nodes.iter().all(|n| buf.all_nodes.contains(n)));
+
+    let yaml = serde_yaml::to_string(&buf).unwrap();
+    let loaded: app::DirectoryBuffer = serde_yaml::from_str(&yaml).unwrap();
+    assert_eq!(loaded, buf);
+    assert!(!loaded.is_truncated());
+
+    let capped: app::ExplorerConfig = serde_yaml::from_str(
+        "{filters: [{filter: RelativePathDoesNotStartWith, input: .}], max_nodes: 3}",
+    )
+    .unwrap();
+    let buf = explorer::explore_sync(capped, dir, None).unwrap();
+    assert_eq!(buf.all_nodes.len(), 3);
+}
+
+#[test]
+fn test_explore_full_extension_filter() {
+    let dir = create_dir(
+        "full_extension_filter",
+        &["a.gz", "b.tar.gz", "c.tgz", "D.TAR.GZ"],
+    );
+
+    let config: app::ExplorerConfig =
+        serde_yaml::from_str("{filters: [{filter: FullExtensionIs, input: tar.gz}]}").unwrap();
+    let buf = explorer::explore_sync(config, dir.clone(), None).unwrap();
+
+    let mut names = buf
+        .nodes
+        .iter()
+        .map(|n| n.relative_path.clone())
+        .collect::<Vec<String>>();
+    names.sort();
+    assert_eq!(names, vec!["D.TAR.GZ".to_string(), "b.tar.gz".to_string()]);
+
+    let config: app::ExplorerConfig = serde_yaml::from_str(
+        "{filters: [{filter: FullExtensionIs, input: tar.gz, case_sensitive: true}]}",
+    )
+    .unwrap();
+    let buf = explorer::explore_sync(config, dir, None).unwrap();
+    assert_eq!(buf.nodes.len(), 1);
+    assert_eq!(buf.nodes[0].relative_path, "b.tar.gz");
+}
+
+#[test]
+fn test_explore_unreadable_directory() {
+    let dir = create_dir("unreadable", &[]);
+    let missing = format!("{}/missing", dir);
+
+    let (tx, rx) = std::sync::mpsc::channel();
+    explorer::explore(app::ExplorerConfig::default(), missing.clone(), None, tx);
+
+    let mut app = app::App::new(config::Config::default(), dir.clone());
+    for task in rx.iter() {
+        app = app.enqueue(task).possibly_mutate().unwrap();
+    }
+
+    assert!(app.logs().iter().any(|l| l.level == app::LogLevel::Error
+        && l.message
+            .starts_with(&format!("failed to explore {}: ", missing))));
+
+    let buf = app.directory_buffers().get(&missing).unwrap();
+    assert!(buf.nodes.is_empty());
+    assert_eq!(buf.focus, 0);
+}
+
+#[test]
+fn test_explore_recursive_max_depth() {
+    let dir = create_dir(
+        "explore_recursive",
+        &["a/", "a/b/", "a/b/c", "a/.d", "e", ".f/", ".f/g"],
+    );
+    let config: app::ExplorerConfig =
+        serde_yaml::from_str("{filters: [{filter: RelativePathDoesNotStartWith, input: .}]}")
+            .unwrap();
+
+    let names = |max_depth| {
+        let buf = explorer::explore_recursive_sync(config.clone(), dir.clone(), max_depth).unwrap();
+        assert!(buf.nodes.iter().all(|n| {
+            let path = std::path::Path::new(&dir).join(&n.relative_path);
+            n.lexical_path() == path && std::path::Path::new(&n.parent) == path.parent().unwrap()
+        }));
+        let mut names = buf
+            .nodes
+            .iter()
+            .map(|n| n.relative_path.clone())
+            .collect::<Vec<String>>();
+        names.sort();
+        names
+    };
+
+    assert_eq!(names(Some(1)), vec!["a", "e"]);
+    assert_eq!(names(Some(2)), vec!["a", "a/b", "e"]);
+    assert_eq!(names(None), vec!["a", "a/b", "a/b/c", "e"]);
+
+    let capped: app::ExplorerConfig = serde_yaml::from_str("{filters: [], max_nodes: 2}").unwrap();
+    let buf = explorer::explore_recursive_sync(capped, dir.clone(), None).unwrap();
+    assert_eq!(buf.all_nodes.len(), 2);
+    assert!(buf.is_truncated());
+}
+
+#[test]
+fn test_explore_recursive_full_extension() {
+    let dir = create_dir("explore_recursive_full_extension", &["v1.2/"]);
+    fs::File::create(format!("{}/v1.2/notes", dir)).unwrap();
+    fs::File::create(format!("{}/v1.2/a.tar.gz", dir)).unwrap();
+
+    let buf = explorer::explore_recursive_sync(app::ExplorerConfig::default(), dir, None).unwrap();
+    let mut extensions = buf
+        .nodes
+        .iter()
+        .map(|n| (n.relative_path.clone(), n.full_extension()))
+        .collect::<Vec<(String, String)>>();
+    extensions.sort();
+
+    assert_eq!(
+        extensions,
+        vec![
+            ("v1.2".into(), "2".into()),
+            ("v1.2/a.tar.gz".into(), "tar.gz".into()),
+            ("v1.2/notes".into(), "".into()),
+        ]
+    );
+}
+
+#[test]
+fn test_explore_recursive_symlinks_as_dirs() {
+    let dir = create_dir("explore_recursive_symlinks", &["real/", "real/a"]);
+    std::os::unix::fs::symlink(format!("{}/real", dir), format!("{}/link", dir)).unwrap();
+    std::os::unix::fs::symlink(&dir, format!("{}/real/up", dir)).unwrap();
+
+    let names = |yaml: &str| {
+        let config: app::ExplorerConfig = serde_yaml::from_str(yaml).unwrap();
+        let mut names = explorer::explore_recursive_sync(config, dir.clone(), None)
+            .unwrap()
+            .nodes
+            .iter()
+            .map(|n| n.relative_path.clone())
+            .collect::<Vec<String>>();
+        names.sort();
+        names
+    };
+
+    assert_eq!(
+        names("{filters: []}"),
+        vec!["link", "real", "real/a", "real/up"]
+    );
+
+    // The links back to a directory being walked are listed, not followed.
+    assert_eq!(
+        names("{filters: [], explore_symlinks_as_dirs: true}"),
+        vec!["link", "link/a", "link/up", "real", "real/a", "real/up",]
+    );
+}
@@ -0,0 +1,145 @@
+use std::fs;
+use std::os::unix::fs::symlink;
+use xplr::*;
+
+mod common;
+use common::create_dir;
+
+#[test]
+fn test_node_symlink_target() {
+    let dir = create_dir("symlink_target", &[]);
+    fs::File::create(format!("{}/file", dir)).unwrap();
+    symlink("file", format!("{}/link", dir)).unwrap();
+    symlink("missing", format!("{}/dangling", dir)).unwrap();
+
+    let file = app::Node::new(dir.clone(), "file".into());
+    assert!(!file.is_symlink);
+    assert_eq!(file.symlink_target, None);
+
+    let link = app::Node::new(dir.clone(), "link".into());
+    assert!(link.is_symlink);
+    assert_eq!(link.symlink_target, Some("file".into()));
+
+    let dangling = app::Node::new(dir, "dangling".into());
+    assert!(dangling.is_symlink);
+    assert_eq!(dangling.symlink_target, Some("missing".into()));
+}
+
+#[test]
+fn test_node_eq_ignores_symlink_target() {
+    let dir = create_dir("symlink_target_eq", &[]);
+    fs::File::create(format!("{}/file", dir)).unwrap();
+    symlink("file", format!("{}/link", dir)).unwrap();
+
+    let link = app::Node::new(dir, "link".into());
+    let mut other = link.clone();
+    other.symlink_target = Some("elsewhere".into());
+    assert_eq!(link, other);
+}
+
+#[test]
+fn test_node_extensions() {
+    let dir = create_dir("node_extensions", &[]);
+    for name in &[".bashrc", "archive.tar.gz", "FILE.TXT"] {
+        fs::File::create(format!("{}/{}", dir, name)).unwrap();
+    }
+
+    let dotfile = app::Node::new(dir.clone(), ".bashrc".into());
+    assert_eq!(dotfile.extension, "");
+    assert_eq!(dotfile.extension_lower, "");
+    assert_eq!(dotfile.full_extension(), "");
+
+    let archive = app::Node::new(dir.clone(), "archive.tar.gz".into());
+    assert_eq!(archive.extension, "gz");
+    assert_eq!(archive.full_extension(), "tar.gz");
+
+    let upper = app::Node::new(dir, "FILE.TXT".into());
+    assert_eq!(upper.extension, "TXT");
+    assert_eq!(upper.extension_lower, "txt");
+    assert_eq!(upper.full_extension(), "TXT");
+}
+
+#[test]
+fn test_node_icon_key() {
+    let dir = create_dir("node_icon_key", &[]);
+    fs::create_dir(format!("{}/dir", dir)).unwrap();
+    for name in &["main.rs", "photo.PNG", "README"] {
+        fs::File::create(format!("{}/{}", dir, name)).unwrap();
+    }
+    symlink("dir", format!("{}/link", dir)).unwrap();
+
+    let key = |name: &str| {
+        app::Node::new(dir.clone(), name.into())
+            .icon_key()
+            .to_string()
+    };
+    assert_eq!(key("dir"), "directory");
+    assert_eq!(key("link"), "symlink");
+    assert_eq!(key("main.rs"), "rs");
+    assert_eq!(key("photo.PNG"), "image");
+    assert_eq!(key("README"), "file");
+}
+
+#[test]
+fn test_node_ordering() {
+    let one = create_dir("node_ordering_1", &[]);
+    let two = create_dir("node_ordering_2", &[]);
+    fs::File::create(format!("{}/a", one)).unwrap();
+    fs::File::create(format!("{}/b", one)).unwrap();
+    fs::create_dir(format!("{}/a", two)).unwrap();
+
+    let a = app::Node::new(one.clone(), "a".into());
+    let b = app::Node::new(one, "b".into());
+    let dir_a = app::Node::new(two, "a".into());
+
+    let mut nodes = vec![b.clone(), dir_a.clone(), a.clone()];
+    nodes.sort();
+    assert_eq!(nodes, vec![a.clone(), dir_a.clone(), b]);
+
+    // Same relative path in different directories are not equal.
+    assert_ne!(a.cmp(&dir_a), std::cmp::Ordering::Equal);
+    assert_eq!(a.cmp(&a.clone()), std::cmp::Ordering::Equal);
+}
+
+#[test]
+fn test_node_display_path() {
+    let home_dir = create_dir("display_path", &[]);
+    fs::create_dir_all(format!("{}/a/b", home_dir)).unwrap();
+    fs::File::create(format!("{}/a/b/c.txt", home_dir)).unwrap();
+    let node = app::Node::new(format!("{}/a/b", home_dir), "c.txt".into());
+    let pwd = format!("{}/a", home_dir);
+    let home = Some(home_dir.as_str());
+
+    let display = |d: app::PathDisplay, pwd: &str| node.display_path(d, pwd, home);
+    assert_eq!(
+        display(app::PathDisplay::Absolute, &pwd),
+        node.absolute_path
+    );
+    assert_eq!(display(app::PathDisplay::RelativeToPwd, &pwd), "b/c.txt");
+    assert_eq!(
+        display(
+            app::PathDisplay::RelativeToPwd,
+            &format!("{}/a/d", home_dir)
+        ),
+        "../b/c.txt"
+    );
+    assert_eq!(display(app::PathDisplay::HomeTilde, &pwd), "~/a/b/c.txt");
+
+    // Outside of the home directory.
+    assert_eq!(
+        node.display_path(app::PathDisplay::HomeTilde, &pwd, Some("/nonexistent")),
+        node.absolute_path
+    );
+    let home_node = app::Node::new(
+        std::path::Path::new(&home_dir)
+            .parent()
+            .unwrap()
+            .to_string_lossy()
+            .to_string(),
+        "display_path".into(),
+    );
+    assert_eq!(
+        home_node.display_path(app::PathDisplay::HomeTilde, &pwd, home),
+        "~"
+    );
+}
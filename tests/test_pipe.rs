@@ -0,0 +1,35 @@
+use xplr::*;
+
+fn handle(app: app::App, msg: app::ExternalMsg) -> app::App {
+    app.enqueue(app::Task::new(0, app::MsgIn::External(msg), None))
+        .possibly_mutate()
+        .unwrap()
+}
+
+// Kept in its own test binary, as the pipes are shared by all the apps in the
+// same process.
+#[test]
+fn test_switch_mode_writes_mode_out() {
+    let app = app::App::new(
+        config::Config::default(),
+        std::env::temp_dir().to_string_lossy().into(),
+    );
+    let mode_out = app.pipe().mode_out.clone();
+
+    let app = handle(app, app::ExternalMsg::SwitchMode("go to".into()));
+    assert_eq!(std::fs::read_to_string(&mode_out).unwrap(), "go to");
+
+    let app = handle(
+        app,
+        app::ExternalMsg::SwitchModeKeepingInput("search".into()),
+    );
+    assert_eq!(std::fs::read_to_string(&mode_out).unwrap(), "search");
+
+    // Only when the mode changes.
+    std::fs::write(&mode_out, "").unwrap();
+    let app = handle(app, app::ExternalMsg::SwitchMode("search".into()));
+    assert_eq!(std::fs::read_to_string(&mode_out).unwrap(), "");
+
+    handle(app, app::ExternalMsg::SwitchMode("default".into()));
+    assert_eq!(std::fs::read_to_string(&mode_out).unwrap(), "default");
+}